@@ -87,7 +87,7 @@ fn record(c: &mut Criterion) {
     // Bench populate the record's track with beats /////////////////////////////////////
     let populate = || {
         for i in 0..BEAT_CAP {
-            let t = SystemTime::now().checked_add(*SEC * i as u32).unwrap();
+            let t = SystemTime::now().checked_sub(*SEC * (BEAT_CAP - i) as u32).unwrap();
             let _ = beat.from(t);
         }
     };
@@ -100,6 +100,18 @@ fn record(c: &mut Criterion) {
     };
     group.bench_function("record_retrieval", |b| b.iter(|| retrieve()));
 
+    // Bench retrieval of the shared snapshot, without copying the record ///////////////
+    let retrieve_arc = || {
+        let _ = dj.get_record_arc(id);
+    };
+    group.bench_function("record_retrieval_arc", |b| b.iter(&retrieve_arc));
+
+    // Bench a query run against the record in place ////////////////////////////////////
+    let query = || {
+        let _ = dj.with_record(id, |r| r.is_optimal());
+    };
+    group.bench_function("with_record", |b| b.iter(&query));
+
     // From this point use the same one record object ///////////////////////////////////
     let record = dj.get_record(id).unwrap(); 
 
@@ -21,7 +21,7 @@ fn main() {
     smol::block_on(async {
         
         // Generate the simple beat 
-        let beat = dj.spin_new("example".to_string()).unwrap();
+        let beat = dj.spin_new_async("example".to_string()).await.unwrap();
 
         // Init a task to monitor
         smol::spawn(async move{run_task(beat).await}).detach();
@@ -0,0 +1,41 @@
+use std::time::SystemTime;
+use std::sync::{Arc, RwLock};
+use std::collections::VecDeque;
+
+use crate::{Record, HealthState, ARCHIVE_CAP};
+
+// ////////////////////////////////////////////////////////////////////////
+// Archive
+// /////////////////////////////////////////////////////////////
+
+// Records removed from the record map, whether purged after their retention
+// period or unregistered, end up here for post-mortem inspection. The Deck
+// writes to it, TheDJ and the reports read from it. Only the most recent
+// <ARCHIVE_CAP> records are kept.
+pub type Archive = Arc<RwLock<VecDeque<Archived>>>;
+
+#[derive(Clone, Debug)]
+pub struct Archived {
+    pub seq: u64,               // Order the records were archived in, starting at 1
+    pub record: Record,         // Final state of the record, including its track
+    pub cause: HealthState,     // Terminal state the record ended in
+    pub ended: SystemTime,      // When the record entered the terminal state
+    pub archived: SystemTime,   // When the record was removed from the record map
+}
+
+// Move a record into the archive, dropping the oldest if it is full
+pub fn archive_record(archive: &Archive, record: Record, now: SystemTime) {
+    if let Ok(mut archive) = archive.write() {
+        let seq = archive.back().map_or(1, |a| a.seq + 1);
+        archive.push_back(Archived {
+            seq,
+            cause: record.health.state,
+            ended: record.health.since,
+            archived: now,
+            record,
+        });
+        while archive.len() > ARCHIVE_CAP {
+            archive.pop_front();
+        }
+    }
+}
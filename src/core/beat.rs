@@ -1,9 +1,10 @@
 
 use std::time::{Duration, SystemTime};
-use std::sync::mpsc;
+use std::sync::{Arc, mpsc};
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
 
-// use crate::{Result, TE, DM2Deck, ConfidenceLevel::UserDefined};
-use crate::{Result, TE, DM2Deck};
+use crate::{Result, TE, DM2Deck, Outcome, HealthState, HealthThresholds, Deadline, Tolerance, Schedule, SharedClock, Handle, BeatRing, RESERVED_FIELDS};
 
 // ////////////////////////////////////////////////////////////////////////
 // Beat 
@@ -12,63 +13,229 @@ use crate::{Result, TE, DM2Deck};
 // This will be owned by the process/loop we are going to monitor. It is used to 
 // send heart beats back to the monitoring runtime.
 pub struct Beat {
-    pub sender: mpsc::Sender<DM2Deck>,
+    pub sender: mpsc::SyncSender<DM2Deck>,  // Only disconnected once the Deck has shut down
     pub id: i32,
+    pub generation: u32,    // Of the id, messages for an earlier record with it are rejected
+    pub clock: SharedClock,
+    pub ring: Arc<BeatRing>,    // Beats from now() go here, for the Deck to drain
+    pub reply_timeout: Duration, // How long to wait on the Deck for a reply
+    pub(crate) completed: bool,
 }
 
 impl Beat {
 
+	pub fn handle(&self) -> Handle {
+		Handle { id: self.id, generation: self.generation }
+	}
+
 	// I'd like to determine when the Beat has changed ownership, or moved
 	// This is a temp solution until we figure something better out
 	pub fn deploy(&self) -> Result<()> {
-		match self.sender.send(DM2Deck::Deploy(self.id, SystemTime::now())) {
-			Err(e) => Err(TE::DM2DeckSendFail(e)),
+		match self.sender.send(DM2Deck::Deploy(self.handle(), self.clock.now())) {
+			Err(_) => Err(TE::ShutDown),
 			_ => Ok(()),
 		}
 	}
 
+	// Beat now. Written straight into the ring rather than sent to the Deck, so
+	// it is cheap enough for tight loops. The Deck picks it up within a DRAIN_TICK.
+	// Only a Deck that has shut down is reported back, one that has died without
+	// closing the ring can't be told apart from one that is busy, see
+	// TheDJ::runtime_health.
     pub fn now(&self) -> Result<()> {
-        if let Err(e) = self.sender.send(
-			DM2Deck::Ping(self.id, SystemTime::now())
-		) { 
-			Err(TE::DM2DeckSendFail(e)) 
-		} else { 
-			Ok(()) 
-		}
+		if self.ring.is_closed() { return Err(TE::ShutDown) };
+		self.ring.push(self.clock.monotonic());
+		Ok(())
     }
 
+	// Beat now with a few named numbers, such as a queue depth or the items
+	// processed since the last beat. Names the exports use themselves, see
+	// RESERVED_FIELDS, are refused.
+	pub fn now_with(&self, fields: &[(&str, f64)]) -> Result<()> {
+		if let Some((k, _)) = fields.iter().find(|(k, _)| RESERVED_FIELDS.contains(k)) {
+			return Err(TE::ReservedField(k.to_string()))
+		}
+		let fields = fields.iter().map(|(k, v)| (k.to_string(), *v)).collect();
+		match self.sender.send(DM2Deck::PingWith(self.handle(), self.clock.stamp(), fields)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Beat in place of now() when the iteration failed. The task is still on
+	// time, but the record is rated as failing if this keeps happening.
+	pub fn fail(&self, reason: &str) -> Result<()> {
+		match self.sender.send(DM2Deck::PingOutcome(self.handle(), self.clock.stamp(), Outcome::Failed(reason.to_string()))) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Beat in place of now() when the iteration worked, but not as it should
+	pub fn degraded(&self, reason: &str) -> Result<()> {
+		match self.sender.send(DM2Deck::PingOutcome(self.handle(), self.clock.stamp(), Outcome::Degraded(reason.to_string()))) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Beat from a timestamp other than now, such as a beat that happened while
+	// offline. Unlike now() this waits on the Deck, so a beat that is a duplicate,
+	// in the future or too old is rejected back to the caller.
     pub fn from(&self, timestamp: SystemTime) -> Result<()> {
-        if let Err(e) = self.sender.send(
-			DM2Deck::Ping(self.id, timestamp)
-		) { 
-			// Err(TE::DM2DeckSendFail(e)) 
-			Err(TE::DM2DeckSendFail(e)) 
-		} else { 
-			Ok(()) 
+		let (tx, rx) = mpsc::channel();
+        if self.sender.send(
+			DM2Deck::PingFrom(self.handle(), self.clock.stamp_at(timestamp), tx)
+		).is_err() { 
+			return Err(TE::ShutDown)
+		}
+		match rx.recv_timeout(self.reply_timeout) {
+			Ok(res) => res,
+			// Dropped unanswered while shutting down
+			Err(RecvTimeoutError::Disconnected) if self.ring.is_closed() => Err(TE::ShutDown),
+			Err(e) => Err(TE::ChanRecvTimeout(e)),
 		}
     }
 
+	// Beat at the start of a span of work, the returned guard marks its finish
+	// when dropped or finished. The Record keeps how long each span took.
+	pub fn span(&self) -> Result<Span<'_>> {
+		match self.sender.send(DM2Deck::SpanStart(self.handle(), self.clock.stamp())) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(Span { beat: self, finished: false }),
+		}
+	}
+
+	// The task finished as intended. The record is kept as Completed for the
+	// retention period rather than as Abandoned.
+	pub fn complete(mut self) -> Result<()> {
+		self.completed = true;
+		match self.sender.send(DM2Deck::Terminate(self.handle(), HealthState::Completed)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
 	pub fn set_expected_freq(&self, expected: Duration) -> Result<()> {
-		// match self.sender.send(DM2Deck::SetExpectedFreq(self.id, expected, UserDefined)) {
-		match self.sender.send(DM2Deck::SetExpectedFreq(self.id, expected)) {
-			Err(e) => Err(TE::DM2DeckSendFail(e)),
+		match self.sender.send(DM2Deck::SetExpectedFreq(self.handle(), expected)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// For beats that are not evenly spaced, the expected millis between each
+	// beat. The pattern repeats, e.g. [100, 100, 100, 100, 5000] for bursts of
+	// five beats followed by a long pause.
+	pub fn set_expected_pattern(&self, expected: Vec<usize>) -> Result<()> {
+		match self.sender.send(DM2Deck::SetExpectedPattern(self.handle(), expected)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn set_health_thresholds(&self, thresholds: HealthThresholds) -> Result<()> {
+		thresholds.validate()?;
+		match self.sender.send(DM2Deck::SetHealthThresholds(self.handle(), thresholds)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn set_tolerance(&self, tolerance: Tolerance) -> Result<()> {
+		tolerance.validate()?;
+		match self.sender.send(DM2Deck::SetTolerance(self.handle(), tolerance)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Expect a beat within `grace` of every run of a cron style schedule, such as
+	// "0 2 * * *" for 02:00 daily
+	pub fn set_schedule(&self, schedule: &str, grace: Duration) -> Result<()> {
+		let schedule = Schedule::parse(schedule)?;
+		match self.sender.send(DM2Deck::SetSchedule(self.handle(), schedule, grace)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Beats sent from a timestamp older than this are rejected, None for no limit
+	pub fn set_max_beat_age(&self, max_age: Option<Duration>) -> Result<()> {
+		match self.sender.send(DM2Deck::SetMaxBeatAge(self.handle(), max_age)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	pub fn set_deadline(&self, deadline: Deadline) -> Result<()> {
+		deadline.validate()?;
+		match self.sender.send(DM2Deck::SetDeadline(self.handle(), deadline)) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+
+	// Grace period after deployment during which the record is not rated or
+	// reported on
+	pub fn set_warmup(&self, warmup: Duration) -> Result<()> {
+		match self.sender.send(DM2Deck::SetWarmup(self.handle(), warmup)) {
+			Err(_) => Err(TE::ShutDown),
 			_ => Ok(()),
 		}
 	}
 
 	pub fn set_deployment(&self, deployment: SystemTime) -> Result<()> {
-		match self.sender.send(DM2Deck::Deploy(self.id, deployment)) {
-			Err(e) => Err(TE::DM2DeckSendFail(e)),
+		match self.sender.send(DM2Deck::Deploy(self.handle(), deployment)) {
+			Err(_) => Err(TE::ShutDown),
 			_ => Ok(()),
 		}
 	}
 
 }
 
-// Upon the Beat being dropped...remove it from the record map
+// Upon the Beat being dropped...mark the record as terminal. Unless the task
+// completed, the drop was either caused by a panic or is unexpected.
 impl Drop for Beat {
 	fn drop(&mut self) {
-		let _ = self.sender.send(DM2Deck::Deregistration(self.id));
+		if self.completed { return };
+		let state = match thread::panicking() {
+			true => HealthState::Panicked,
+			false => HealthState::Abandoned,
+		};
+		let _ = self.sender.send(DM2Deck::Terminate(self.handle(), state));
     }
 }
-		
+
+// ////////////////////////////////////////////////////////////////////////
+// Span
+// /////////////////////////////////////////////////////////////
+
+// Guard over a span of work, started by Beat::span
+pub struct Span<'a> {
+	beat: &'a Beat,
+	finished: bool,
+}
+
+impl Span<'_> {
+
+	// Finish the span now, rather than when the guard is dropped
+	pub fn finish(mut self) -> Result<()> {
+		self.finished = true;
+		self.send_end()
+	}
+
+	fn send_end(&self) -> Result<()> {
+		match self.beat.sender.send(DM2Deck::SpanEnd(self.beat.handle(), self.beat.clock.stamp())) {
+			Err(_) => Err(TE::ShutDown),
+			_ => Ok(()),
+		}
+	}
+}
+
+impl Drop for Span<'_> {
+	fn drop(&mut self) {
+		if !self.finished {
+			let _ = self.send_end();
+		}
+	}
+}
@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use crate::{TE, Result, TheDJ, Report, SharedClock, SystemClock};
+use crate::{RECORD_CAP, BEAT_CAP, DECK_TICK, DRAIN_TICK, OUTPUT_TICK, CHANNEL_BOUND, REPLY_TIMEOUT};
+use crate::{STALL_THRESHOLD, STALL_TICKS};
+
+// ////////////////////////////////////////////////////////////////////////
+// Config
+// /////////////////////////////////////////////////////////////
+
+// How the runtimes are set up, the crate constants unless built otherwise
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Config {
+    pub record_cap: usize,          // Most records kept at once
+    pub track_len: usize,           // Beats kept per record, unless the record says otherwise
+    pub deck_tick: Duration,        // How often the Deck evaluates the health of every record
+    pub drain_tick: Duration,       // How often the Deck drains the Beats' rings
+    pub output_tick: Duration,      // How often the Output checks for reports that are due
+    pub channel_bound: usize,       // Messages queued for the Deck before senders wait
+    pub reply_timeout: Duration,    // How long to wait on the Deck for a reply
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            record_cap: RECORD_CAP,
+            track_len: BEAT_CAP,
+            deck_tick: DECK_TICK,
+            drain_tick: DRAIN_TICK,
+            output_tick: OUTPUT_TICK,
+            channel_bound: CHANNEL_BOUND,
+            reply_timeout: REPLY_TIMEOUT,
+        }
+    }
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<()> {
+        if self.record_cap == 0 || self.record_cap > i32::MAX as usize {
+            return Err(TE::InvalidConfig("The record cap must be between 1 and i32::MAX"))
+        }
+        if self.track_len == 0 {
+            return Err(TE::InvalidConfig("The track length must be above zero"))
+        }
+        if self.deck_tick.is_zero() || self.drain_tick.is_zero() || self.output_tick.is_zero() {
+            return Err(TE::InvalidConfig("Ticks must be above zero"))
+        }
+        if self.channel_bound == 0 {
+            return Err(TE::InvalidConfig("The channel bound must be above zero"))
+        }
+        Ok(())
+    }
+
+    // How long the Deck may go without going round its loop before it is
+    // Stalled. It goes round at least once a tick, so longer ticks allow longer.
+    pub fn deck_stall(&self) -> Duration {
+        STALL_THRESHOLD.max(self.deck_tick.min(self.drain_tick).saturating_mul(STALL_TICKS))
+    }
+
+    // As deck_stall, for the Output
+    pub fn output_stall(&self) -> Duration {
+        STALL_THRESHOLD.max(self.output_tick.saturating_mul(STALL_TICKS))
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////
+// Builder
+// /////////////////////////////////////////////////////////////
+
+// Builds TheDJ, see TheDJ::builder(). The Output is only run when reporting,
+// which handing it a report turns on.
+pub struct TheDJBuilder {
+    pub(crate) config: Config,
+    pub(crate) clock: SharedClock,
+    pub(crate) reports: Vec<Box<dyn Report>>,
+    pub(crate) reporting: bool,
+}
+
+impl Default for TheDJBuilder {
+    fn default() -> Self {
+        TheDJBuilder {
+            config: Config::default(),
+            clock: SystemClock::shared(),
+            reports: Vec::new(),
+            reporting: false,
+        }
+    }
+}
+
+impl TheDJBuilder {
+
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn record_cap(mut self, record_cap: usize) -> Self {
+        self.config.record_cap = record_cap;
+        self
+    }
+
+    pub fn track_len(mut self, track_len: usize) -> Self {
+        self.config.track_len = track_len;
+        self
+    }
+
+    pub fn deck_tick(mut self, tick: Duration) -> Self {
+        self.config.deck_tick = tick;
+        self
+    }
+
+    pub fn drain_tick(mut self, tick: Duration) -> Self {
+        self.config.drain_tick = tick;
+        self
+    }
+
+    pub fn output_tick(mut self, tick: Duration) -> Self {
+        self.config.output_tick = tick;
+        self
+    }
+
+    pub fn channel_bound(mut self, bound: usize) -> Self {
+        self.config.channel_bound = bound;
+        self
+    }
+
+    pub fn reply_timeout(mut self, timeout: Duration) -> Self {
+        self.config.reply_timeout = timeout;
+        self
+    }
+
+    // The source of time, such as a MockClock
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn reporting(mut self, reporting: bool) -> Self {
+        self.reporting = reporting;
+        self
+    }
+
+    // A report to run from the start, turns on reporting
+    pub fn report(mut self, report: Box<dyn Report>) -> Self {
+        self.reports.push(report);
+        self.reporting = true;
+        self
+    }
+
+    pub fn build(self) -> Result<TheDJ> {
+        self.config.validate()?;
+        TheDJ::init_(self)
+    }
+}
+
+impl std::fmt::Debug for TheDJBuilder {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "TheDJBuilder {{ config: {:?}, clock: {:?}, reports: {}, reporting: {} }}",
+            self.config, self.clock, self.reports.len(), self.reporting)
+    }
+}
@@ -0,0 +1,123 @@
+use std::time::{Duration, SystemTime, Instant, UNIX_EPOCH};
+use std::sync::{Arc, Mutex};
+use std::fmt::Debug;
+
+use crate::{Stamp, CLOCK_JUMP_THRESHOLD};
+
+// ////////////////////////////////////////////////////////////////////////
+// Clock
+// /////////////////////////////////////////////////////////////
+
+// The source of time for beats, records and reports. TheDJ hands its clock to
+// the runtimes and to every Beat it spins up, so swapping in a MockClock lets
+// whole scenarios play out without waiting on real time.
+//
+// A clock keeps two times. The wall clock, which may be stepped by NTP or a
+// user, is only used for exporting and calendars. The monotonic timeline never
+// goes backwards and is what durations between beats are measured on. It is
+// expressed as a duration since the unix epoch, starting out level with the
+// wall clock, so the two only drift apart when the wall clock is stepped.
+pub trait Clock: Send + Sync + Debug {
+    fn now(&self) -> SystemTime;
+    fn monotonic(&self) -> Duration;
+
+    fn stamp(&self) -> Stamp {
+        Stamp { mono: self.monotonic(), wall: self.now() }
+    }
+
+    // Stamp a wall clock time other than now, such as a historical beat. While
+    // the wall clock agrees with the monotonic timeline it maps straight onto it,
+    // once the wall clock has been stepped it is placed relative to now instead.
+    fn stamp_at(&self, wall: SystemTime) -> Stamp {
+        let now = self.stamp();
+        let now_wall = now.wall.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let skew = now_wall.abs_diff(now.mono);
+        let mono = match now.wall.duration_since(wall) {
+            _ if skew <= CLOCK_JUMP_THRESHOLD => wall.duration_since(UNIX_EPOCH).unwrap_or_default(),
+            Ok(ago) => now.mono.checked_sub(ago).unwrap_or_default(),
+            Err(e) => now.mono + e.duration(),
+        };
+        Stamp { mono, wall }
+    }
+}
+
+pub type SharedClock = Arc<dyn Clock>;
+
+// Wall clock time, the default
+#[derive(Debug, Clone, Copy)]
+pub struct SystemClock {
+    origin: Instant,
+    origin_wall: Duration,
+}
+
+impl Default for SystemClock {
+    fn default() -> Self {
+        SystemClock {
+            origin: Instant::now(),
+            origin_wall: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+        }
+    }
+}
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.origin_wall + self.origin.elapsed()
+    }
+}
+
+impl SystemClock {
+    pub fn shared() -> SharedClock {
+        Arc::new(SystemClock::default())
+    }
+}
+
+// A clock that only moves when told to. Clones share the same time, so one
+// can be handed to TheDJ while the other is kept to advance it.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<Stamp>>,
+}
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        let mono = start.duration_since(UNIX_EPOCH).unwrap_or_default();
+        MockClock { now: Arc::new(Mutex::new(Stamp { mono, wall: start })) }
+    }
+
+    // Move both the wall clock and the monotonic timeline forward
+    pub fn advance(&self, by: Duration) {
+        if let Ok(mut now) = self.now.lock() {
+            now.mono += by;
+            now.wall += by;
+        }
+    }
+
+    // Step only the wall clock, as NTP or a user might
+    pub fn step_wall(&self, to: SystemTime) {
+        if let Ok(mut now) = self.now.lock() {
+            now.wall = to;
+        }
+    }
+
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        self.now.lock().expect("MockClock poisoned").wall
+    }
+
+    fn monotonic(&self) -> Duration {
+        self.now.lock().expect("MockClock poisoned").mono
+    }
+
+    fn stamp(&self) -> Stamp {
+        *self.now.lock().expect("MockClock poisoned")
+    }
+}
@@ -1,30 +1,136 @@
-use std::time::{Duration, SystemTime};
-use std::sync::{Arc, RwLock};
-use std::collections::HashMap;
-use std::sync::mpsc::{Receiver, Sender};
-use std::thread;
+use std::time::{Duration, SystemTime, Instant};
+use std::sync::{Arc, RwLock, Weak};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::mpsc::{Receiver, Sender, SendError, RecvTimeoutError, TryRecvError};
+use std::thread::{self, JoinHandle};
+use std::panic::{self, AssertUnwindSafe};
 
-// use crate::{Record, DM2DJ, Indexer, ConfidenceLevel, DM2OutputRunner};
-use crate::{Record, DM2DJ, Indexer, DM2OutputRunner};
+use crate::{Result, TE, Record, Outcome, HealthState, DM2DJ, Indexer, DM2OutputRunner, HealthThresholds, Deadline, Tolerance, Schedule, SharedClock, Stamp, TERMINAL_RETENTION};
+use crate::core::archive::{Archive, archive_record};
+use crate::{BeatRing, Notifier, Vitals, Config};
 
 
 // ////////////////////////////////////////////////////////////////
 // Type less
 // ///////////////////////////////////////////////////
-pub type Arm = Arc<RwLock<HashMap<i32, Record>>>;
+// Records are shared between the Deck's snapshots and whoever reads them, so a
+// record that hasn't changed is never copied
+pub type Arm = Arc<RwLock<HashMap<i32, Arc<Record>>>>;
+
+// Identifies a record. Ids are reused once a record has been removed, the
+// generation tells apart the records that have had the same id so a message
+// meant for an earlier one is not applied to the current one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Handle {
+    pub id: i32,
+    pub generation: u32,
+}
+
+// The current record the handle is for
+fn current(rm: &mut HashMap<i32, Record>, handle: Handle) -> Result<&mut Record> {
+    match rm.get_mut(&handle.id) {
+        Some(record) if record.generation == handle.generation => Ok(record),
+        Some(_) => Err(TE::StaleHandle),
+        None => Err(TE::MissingRecord),
+    }
+}
 
 // ////////////////////////////////////////////////////////////////
 // Enumeration for Messaging the Deck
 // ///////////////////////////////////////////////////
 #[derive(Debug)]
 pub enum DM2Deck {
-    Ping(i32, SystemTime),
-    Registration(String),
-    Deploy(i32, SystemTime),
-    // SetExpectedFreq(i32, Duration, ConfidenceLevel),
-    SetExpectedFreq(i32, Duration),
-    Deregistration(i32),
-    Init()
+    Ping(Handle, Stamp),
+    PingFrom(Handle, Stamp, Sender<Result<()>>),   // Validated, the outcome is sent back
+    PingWith(Handle, Stamp, Vec<(String, f64)>),   // With named numeric fields
+    PingOutcome(Handle, Stamp, Outcome),           // With a failure or degraded status
+    SpanStart(Handle, Stamp),
+    SpanEnd(Handle, Stamp),
+    Registration(String, Arc<BeatRing>, Option<usize>, Sender<Result<Handle>>),   // Track length, if not the default. The id is sent back
+    Deploy(Handle, SystemTime),
+    SetWarmup(Handle, Duration),
+    SetExpectedFreq(Handle, Duration),
+    SetExpectedPattern(Handle, Vec<usize>),
+    SetHealthThresholds(Handle, HealthThresholds),
+    SetDeadline(Handle, Deadline),
+    SetTolerance(Handle, Tolerance),
+    SetSchedule(Handle, Schedule, Duration),
+    SetMaxBeatAge(Handle, Option<Duration>),
+    Terminate(Handle, HealthState),    // The Beat is gone, keep the record in a terminal state
+    SetRetention(Duration),         // How long terminal records are kept for
+    Deregistration(Handle),
+    Init(),
+    Tick(Sender<Result<()>>),           // Evaluate every record now, replied to once done
+    Shutdown(Sender<Result<()>>),       // Replied to once the Deck has stopped
+}
+
+impl DM2Deck {
+    // The record the message is for, if any
+    fn handle(&self) -> Option<Handle> {
+        match self {
+            DM2Deck::Ping(h, ..) | DM2Deck::PingFrom(h, ..) | DM2Deck::PingWith(h, ..)
+            | DM2Deck::PingOutcome(h, ..) | DM2Deck::SpanStart(h, ..) | DM2Deck::SpanEnd(h, ..)
+            | DM2Deck::Deploy(h, ..) | DM2Deck::SetWarmup(h, ..) | DM2Deck::SetExpectedFreq(h, ..)
+            | DM2Deck::SetExpectedPattern(h, ..) | DM2Deck::SetHealthThresholds(h, ..)
+            | DM2Deck::SetDeadline(h, ..) | DM2Deck::SetTolerance(h, ..) | DM2Deck::SetSchedule(h, ..)
+            | DM2Deck::SetMaxBeatAge(h, ..) | DM2Deck::Terminate(h, ..) | DM2Deck::Deregistration(h) => Some(*h),
+            DM2Deck::Registration(..) | DM2Deck::SetRetention(..) | DM2Deck::Init()
+            | DM2Deck::Tick(..) | DM2Deck::Shutdown(..) => None,
+        }
+    }
+}
+
+// Move the beats waiting in the rings into their records, noting which changed
+fn drain_rings(rm: &mut HashMap<i32, Record>, rings: &HashMap<i32, Arc<BeatRing>>, clock: &SharedClock, dirty: &mut HashSet<i32>) {
+    let now = clock.stamp();
+    for (id, ring) in rings.iter() {
+        let n = match rm.get_mut(id) {
+            Some(n) => n,
+            None => continue,
+        };
+        let mut added = 0;
+        let lost = ring.drain(|mono| {
+            // Only the monotonic time is kept in the ring, the wall clock time
+            // is worked back from now
+            let wall = now.wall.checked_sub(now.mono.saturating_sub(mono)).unwrap_or(now.wall);
+            if n.add_stamp(Stamp { mono, wall }).is_ok() {
+                n.add_outcome(wall, Outcome::Success);
+                added += 1;
+            }
+        });
+        n.lost_beats += lost;
+        if added > 0 {
+            n.infer_freq();
+            n.update_health();
+        }
+        if added > 0 || lost > 0 { dirty.insert(*id); };
+    }
+}
+
+// Stop draining a removed record's ring. The Beat may still be writing into it,
+// so it is kept track of until shutdown closes it.
+fn retire(rings: &mut HashMap<i32, Arc<BeatRing>>, retired: &mut Vec<Weak<BeatRing>>, id: i32) {
+    if let Some(ring) = rings.remove(&id) {
+        retired.retain(|r| r.strong_count() > 0);
+        retired.push(Arc::downgrade(&ring));
+    }
+}
+
+// Bring the snapshot in the ARM up to date with the dirty records. Only those
+// are copied, everything else is still shared with the previous snapshot.
+fn publish(arm: &Arm, rm: &HashMap<i32, Record>, dirty: &mut HashSet<i32>) {
+    if dirty.is_empty() { return };
+    let updates = dirty.drain()
+        .map(|id| (id, rm.get(&id).map(|r| Arc::new(r.clone()))))
+        .collect::<Vec<(i32, Option<Arc<Record>>)>>();
+    if let Ok(mut arm) = arm.write() {
+        for (id, record) in updates {
+            match record {
+                Some(record) => { arm.insert(id, record); },
+                None => { arm.remove(&id); },
+            }
+        }
+    }
 }
 
 // ////////////////////////////////////////////////////////////////
@@ -36,82 +142,396 @@ impl Deck {
     pub fn run(rx: Receiver<DM2Deck>, 
                dj_tx: Sender<DM2DJ>,
                outputrunner_tx: Sender<DM2OutputRunner>,
-            ) {
+               clock: SharedClock,
+            ) -> JoinHandle<()> {
+        Self::run_with(rx, dj_tx, outputrunner_tx, clock, Config::default())
+    }
+
+    pub fn run_with(rx: Receiver<DM2Deck>, 
+               dj_tx: Sender<DM2DJ>,
+               outputrunner_tx: Sender<DM2OutputRunner>,
+               clock: SharedClock,
+               config: Config,
+            ) -> JoinHandle<()> {
 
         // Spawn a new thread owning the core data.
-        // Currently we don't have errors bubbling out and will need
-        // to implement that. TODO !!
-        thread::spawn(move || {
+        // A panic is caught and the Deck carries on from the record map it last
+        // published, see Vitals.
+        thread::Builder::new().name(String::from("thumper-deck")).spawn(move || {
                 
             // Init the indexer, which manages distribution of ID numbers
-            let mut indexer = Indexer::new();
+            let mut indexer = Indexer::with_cap(config.record_cap);
 
             // Record map and atomic variants
             let mut rm: HashMap<i32, Record> = HashMap::new();
-            let arm = Arc::new(RwLock::new(rm.clone()));
+            let arm: Arm = Arc::new(RwLock::new(HashMap::new()));
             let arm2 = arm.clone();
 
-            loop {    
-                if let Ok(call) = rx.recv() { 
-                    match call {
+            // Records removed from the record map
+            let archive: Archive = Arc::new(RwLock::new(VecDeque::new()));
+
+            // Wakes the futures waiting on the Deck
+            let notifier = Arc::new(Notifier::new());
+
+            // Restarts and faults, for TheDJ::runtime_health
+            let vitals = Arc::new(Vitals::new());
+
+            // The ring each current record's Beat writes its beats into
+            let mut rings: HashMap<i32, Arc<BeatRing>> = HashMap::new();
+
+            // Rings of removed records, that Beats no longer around don't hold
+            let mut retired: Vec<Weak<BeatRing>> = Vec::new();
+
+            // When the health of every record was last evaluated, on the clock's
+            // monotonic timeline so a MockClock decides when it is due
+            let mut last_eval = clock.monotonic();
+
+            // How long records are kept once their task is gone
+            let mut retention = TERMINAL_RETENTION;
+
+            // Records changed since the ARM was last brought up to date
+            let mut dirty: HashSet<i32> = HashSet::new();
+
+            // Set once asked to shut down, to reply to when done
+            let mut stopping: Option<Sender<Result<()>>> = None;
+
+            // Messages still taken while shutting down. No more than could be
+            // queued, so clones that keep sending can't keep the Deck going.
+            let mut draining = 0;
+
+            while panic::catch_unwind(AssertUnwindSafe(|| loop {    
+                vitals.deck_alive();
+                // Wake up at least once a tick, as time passing alone can change
+                // the health of a record. More often to drain the rings.
+                let since_eval = clock.monotonic().saturating_sub(last_eval);
+                let wait = config.deck_tick.checked_sub(since_eval).unwrap_or_default().min(config.drain_tick);
+                let mut reply = None;
+                let mut ticked = false;
+                let mut registered = None;
+                // While shutting down only what is already queued is taken
+                let received = match stopping {
+                    None => rx.recv_timeout(wait),
+                    Some(_) if draining == 0 => Err(RecvTimeoutError::Timeout),
+                    Some(_) => {
+                        draining -= 1;
+                        rx.try_recv().map_err(|e| match e {
+                            TryRecvError::Empty => RecvTimeoutError::Timeout,
+                            TryRecvError::Disconnected => RecvTimeoutError::Disconnected,
+                        })
+                    },
+                };
+                let idle = matches!(received, Err(RecvTimeoutError::Timeout));
+
+                // Sent after the shutdown, the records are no longer changed
+                let received = match received {
+                    Ok(DM2Deck::PingFrom(_, _, tx)) if stopping.is_some() => {
+                        let _ = tx.send(Err(TE::ShutDown));
+                        Err(RecvTimeoutError::Timeout)
+                    },
+                    Ok(call) if stopping.is_some() && call.handle().is_some() => Err(RecvTimeoutError::Timeout),
+                    received => received,
+                };
+                let target = received.as_ref().ok().and_then(|call| call.handle());
+
+                // Drain the rings before acting on the message, any beats written
+                // before it was sent are then in place
+                drain_rings(&mut rm, &rings, &clock, &mut dirty);
+                let changed = match received { 
+                    Ok(call) => match call {
                         DM2Deck::Init() => {
                             if let Err(e) =  dj_tx.send(DM2DJ::ARM(arm2.clone())) {
                                 panic!("TX to DJ failed: {:?}", e)
                             } 
+                            if let Err(e) =  dj_tx.send(DM2DJ::Archive(archive.clone())) {
+                                panic!("TX to DJ failed: {:?}", e)
+                            } 
+                            if let Err(e) =  dj_tx.send(DM2DJ::Notifier(notifier.clone())) {
+                                panic!("TX to DJ failed: {:?}", e)
+                            } 
+                            if let Err(e) =  dj_tx.send(DM2DJ::Vitals(vitals.clone())) {
+                                panic!("TX to DJ failed: {:?}", e)
+                            } 
+                            false
                         },
-                        DM2Deck::Deploy(id, time) => {
-                            if let Some(n) = rm.get_mut(&id) {
+                        DM2Deck::Deploy(handle, time) => {
+                            if let Ok(n) = current(&mut rm, handle) {
                                 n.set_deployment(time);
-                            } else {
-                                continue
-                            }
+                                true
+                            } else { false }
                         },
-                        // DM2Deck::SetExpectedFreq(id, expected, confidence_level) => {
-                        DM2Deck::SetExpectedFreq(id, expected) => {
-                            if let Some(n) = rm.get_mut(&id) {
-                                // n.set_expected_freq(expected, confidence_level);
+                        DM2Deck::SetWarmup(handle, warmup) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_warmup(warmup);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::SetExpectedFreq(handle, expected) => {
+                            if let Ok(n) = current(&mut rm, handle) {
                                 n.set_expected_freq(expected);
-                            } else {
-                                continue
-                            }
+                                true
+                            } else { false }
                         },
-                        DM2Deck::Ping(id, time) => {
-                            if let Some(n) = rm.get_mut(&id) {
-                                n.add_beat(time);
-                            } else {
-                                continue
-                            }
+                        DM2Deck::SetExpectedPattern(handle, expected) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_expected_pattern(expected);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::SetHealthThresholds(handle, thresholds) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_health_thresholds(thresholds);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::SetDeadline(handle, deadline) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_deadline(deadline);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::SetTolerance(handle, tolerance) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_tolerance(tolerance);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::SetSchedule(handle, schedule, grace) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_schedule(schedule, grace);
+                                true
+                            } else { false }
                         },
-                        DM2Deck::Registration(name) => {
-                            match indexer.next() {
-                                Ok(id) => { 
-                                    rm.insert(id, Record::new(name, id)); 
-                                    if let Err(_e) =  dj_tx.send(DM2DJ::ID(Ok(id))) {
-                                        rm.remove(&id);
-                                        break;
-                                    }
-                                },
-                                Err(e) => { let _ = dj_tx.send(DM2DJ::ID(Err(e))); },
+                        DM2Deck::SetMaxBeatAge(handle, max_age) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.set_max_beat_age(max_age);
+                                true
+                            } else { false }
+                        },
+                        DM2Deck::Ping(handle, stamp) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                if n.add_stamp(stamp).is_ok() {
+                                    n.add_outcome(stamp.wall, Outcome::Success);
+                                    n.infer_freq();
+                                    n.update_health();
+                                    true
+                                } else { false }
+                            } else { false }
+                        },
+                        DM2Deck::PingWith(handle, stamp, fields) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                if n.add_stamp(stamp).is_ok() {
+                                    n.add_fields(stamp.wall, fields);
+                                    n.add_outcome(stamp.wall, Outcome::Success);
+                                    n.infer_freq();
+                                    n.update_health();
+                                    true
+                                } else { false }
+                            } else { false }
+                        },
+                        DM2Deck::PingOutcome(handle, stamp, outcome) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                if n.add_stamp(stamp).is_ok() {
+                                    n.add_outcome(stamp.wall, outcome);
+                                    n.infer_freq();
+                                    n.update_health();
+                                    true
+                                } else { false }
+                            } else { false }
+                        },
+                        DM2Deck::SpanStart(handle, stamp) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                if n.start_span(stamp).is_ok() {
+                                    n.add_outcome(stamp.wall, Outcome::Success);
+                                    n.infer_freq();
+                                    n.update_health();
+                                    true
+                                } else { false }
+                            } else { false }
+                        },
+                        DM2Deck::SpanEnd(handle, stamp) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.end_span(stamp).is_some()
+                            } else { false }
+                        },
+                        DM2Deck::PingFrom(handle, stamp, tx) => {
+                            let res = current(&mut rm, handle).and_then(|n| {
+                                n.validate_stamp(&stamp)
+                                    .and_then(|_| n.add_stamp(stamp))
+                                    .map(|_| {
+                                        n.add_outcome(stamp.wall, Outcome::Success);
+                                        n.infer_freq();
+                                        n.update_health();
+                                    })
+                            });
+                            let changed = res.is_ok();
+                            reply = Some((tx, res));
+                            changed
+                        },
+                        DM2Deck::Registration(name, ring, track_len, tx) => {
+                            // Terminal records make way for new ones, oldest first,
+                            // rather than keep the ids for their whole retention
+                            let mut res = match stopping {
+                                None => indexer.next(),
+                                Some(_) => Err(TE::ShutDown),
+                            };
+                            if let Err(TE::MaximumCapacity) = res {
+                                let oldest = rm.values()
+                                    .filter_map(|r| r.terminated.map(|at| (at, r.id)))
+                                    .min()
+                                    .map(|(_, id)| id);
+                                if let Some(record) = oldest.and_then(|id| rm.remove(&id)) {
+                                    retire(&mut rings, &mut retired, record.id);
+                                    indexer.remove(record.id);
+                                    dirty.insert(record.id);
+                                    archive_record(&archive, record, clock.now());
+                                    res = indexer.next();
+                                }
+                            }
+                            if let Ok(handle) = res {
+                                let mut record = Record::new_with_clock(name, handle.id, clock.clone());
+                                record.generation = handle.generation;
+                                record.set_track_len(track_len.unwrap_or(config.track_len));
+                                rm.insert(handle.id, record); 
+                                rings.insert(handle.id, ring);
+                                dirty.insert(handle.id);
                             }
+                            let changed = res.is_ok();
+                            registered = Some((tx, res));
+                            changed
                         }
-                        DM2Deck::Deregistration(id) => {
-                            if let Some(_) = rm.remove(&id) {
-                                indexer.remove(id);
-                            } else {
-                                continue
-                            };
+                        DM2Deck::Terminate(handle, state) => {
+                            if let Ok(n) = current(&mut rm, handle) {
+                                n.terminate(state).is_some()
+                            } else { false }
+                        },
+                        DM2Deck::Tick(tx) => {
+                            reply = Some((tx, Ok(())));
+                            ticked = true;
+                            false
+                        },
+                        DM2Deck::SetRetention(r) => {
+                            retention = r;
+                            false
+                        },
+                        // Stop taking beats, anything still in the rings is
+                        // drained one last time
+                        DM2Deck::Shutdown(tx) => {
+                            rings.values().for_each(|ring| ring.close());
+                            retired.drain(..).filter_map(|r| r.upgrade()).for_each(|ring| ring.close());
+                            stopping = Some(tx);
+                            draining = config.channel_bound;
+                            false
+                        },
+                        DM2Deck::Deregistration(handle) => {
+                            if current(&mut rm, handle).is_ok() {
+                                if let Some(mut record) = rm.remove(&handle.id) {
+                                    record.terminate(HealthState::Unregistered);
+                                    archive_record(&archive, record, clock.now());
+                                }
+                                retire(&mut rings, &mut retired, handle.id);
+                                indexer.remove(handle.id);
+                                true
+                            } else { false }
                         },
-                    };
+                    },
+                    Err(RecvTimeoutError::Timeout) => false,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                if let Some(handle) = target.filter(|_| changed) {
+                    dirty.insert(handle.id);
+                }
 
-                    // At this point we assume _some_ changes have been made and will need to
-                    // update the atomic record map.
-                    if let Ok(mut arm) = arm.write() {
-                        *arm = rm.clone();
+                let now = clock.monotonic();
+                if ticked || now.saturating_sub(last_eval) >= config.deck_tick {
+                    for record in rm.values_mut() {
+                        // Passing a run moves the next one on, missed or not
+                        let next_run = |r: &Record| r.scheduled.as_ref().and_then(|s| s.next_run);
+                        let before = next_run(record);
+                        let missed = record.check_schedule() > 0;
+                        let scheduled = missed || next_run(record) != before;
+                        if record.update_health().is_some() || scheduled {
+                            dirty.insert(record.id);
+                        }
                     }
 
-                } else { break };
-            };
-        });
+                    // Purge the terminal records that have been kept long enough
+                    let expired = rm.values()
+                        .filter(|r| r.is_expired(retention))
+                        .map(|r| r.id)
+                        .collect::<Vec<i32>>();
+                    for id in expired {
+                        if let Some(record) = rm.remove(&id) {
+                            archive_record(&archive, record, clock.now());
+                        }
+                        retire(&mut rings, &mut retired, id);
+                        indexer.remove(id);
+                        dirty.insert(id);
+                    }
+                    last_eval = now;
+                }
+
+                // Update the atomic record map with whatever has changed
+                let woken = !dirty.is_empty() || reply.is_some() || registered.is_some();
+                publish(&arm, &rm, &mut dirty);
+
+                // Reply once the change can be seen through the ARM. A Beat may
+                // have given up waiting.
+                if let Some((tx, res)) = reply {
+                    if tx.send(res).is_err() { Vitals::count(&vitals.dropped_replies) };
+                }
+
+                // Nobody is waiting on a registration, roll it back rather than
+                // keep a record no Beat will ever beat for
+                if let Some((tx, res)) = registered {
+                    if let Err(SendError(Ok(handle))) = tx.send(res) {
+                        Vitals::count(&vitals.dropped_replies);
+                        rm.remove(&handle.id);
+                        retire(&mut rings, &mut retired, handle.id);
+                        indexer.remove(handle.id);
+                        dirty.insert(handle.id);
+                        publish(&arm, &rm, &mut dirty);
+                    }
+                }
+
+                // Futures waiting on the Deck check again once there is news,
+                // otherwise only those that timed out
+                if woken {
+                    notifier.notify();
+                } else {
+                    notifier.expire(Instant::now());
+                }
+
+                // Nothing left queued, the last beats are in the ARM
+                if idle {
+                    if let Some(tx) = stopping.take() {
+                        drain_rings(&mut rm, &rings, &clock, &mut dirty);
+                        publish(&arm, &rm, &mut dirty);
+                        notifier.close();
+                        let _ = tx.send(Ok(()));
+                        break
+                    }
+                }
+            })).is_err() {
+
+                // Whatever the panic left half done is dropped, the records go
+                // back to how they were last published
+                Vitals::count(&vitals.deck_restarts);
+                arm.clear_poison();
+                archive.clear_poison();
+                rm = match arm.read() {
+                    Ok(arm) => arm.iter().map(|(id, record)| (*id, Record::clone(record))).collect(),
+                    Err(_) => HashMap::new(),
+                };
+                let lost = rings.keys().filter(|id| !rm.contains_key(id)).copied().collect::<Vec<i32>>();
+                lost.into_iter().for_each(|id| retire(&mut rings, &mut retired, id));
+                indexer.retain(|id| rm.contains_key(&id));
+                dirty.clear();
+                last_eval = clock.monotonic();
+            }
+
+            // However the Deck stopped, nobody should be left waiting on it
+            notifier.close();
+        }).expect("Could not spawn the Deck")
 
     }
 }
@@ -1,8 +1,13 @@
-use std::time::{SystemTime, Duration};
-use std::thread;
-use std::sync::mpsc;
+use std::time::{SystemTime, Duration, Instant};
+use std::thread::{self, JoinHandle};
+use std::sync::{Arc, Mutex, mpsc};
+use std::sync::mpsc::{TryRecvError, RecvTimeoutError, TrySendError, SendError};
 
-use crate::{Deck, DM2Deck, TE, Result, Record, Arm, DM2OutputRunner, Report, Output, Beat};
+use crate::{Deck, DM2Deck, TE, Result, Record, Arm, Handle, Archive, Archived, DM2OutputRunner, Report, Output, Beat};
+use crate::{HealthState, HealthThresholds, Deadline, Overdue, Tolerance, ConfidenceLevel, Schedule};
+use crate::{SharedClock, BeatRing, Notifier, Vitals, RuntimeHealth, RuntimeState};
+use crate::{TheDJBuilder, RING_CAP, DRAIN_TICK};
+use crate::core::notify::{Until, block_on};
 
 // ////////////////////////////////////////////////////////////////
 // The DJ 
@@ -16,83 +21,150 @@ use crate::{Deck, DM2Deck, TE, Result, Record, Arm, DM2OutputRunner, Report, Out
 //      - Provide API to the runtime through use of channels
 //      - Set up any output ( What about triggers and callbacks)
 //      - Spin up beats which are distrobuted to the concurrent tasks/loops
+//
+// The DJ is a handle onto the runtime, it can be cloned and shared between
+// threads. Every request that waits on the Deck gets its own reply channel, so
+// concurrent requests can't be handed each other's answers.
 
+#[derive(Clone)]
 pub struct TheDJ {
-    rt_tx: mpsc::Sender<DM2Deck>,
-    rt_rx: mpsc::Receiver<DM2DJ>,
+    rt_tx: mpsc::SyncSender<DM2Deck>,  // Only disconnected once the Deck has shut down
     outputrunner_tx: mpsc::Sender<DM2OutputRunner>,
     atomic_record_map: Option<Arm>,
+    archive: Option<Archive>,
+    notifier: Option<Arc<Notifier>>,
+    vitals: Option<Arc<Vitals>>,
+    reporting: bool,
+    clock: SharedClock,
+    reply_timeout: Duration,    // How long to wait on the Deck for a reply
+    stall: (Duration, Duration),    // How long the Deck and the Output may be quiet for, see Config
+    threads: Arc<Mutex<Option<Threads>>>,   // Taken by whichever clone shuts down
 }
 
-// Calls made to the DJ
+// The runtime threads, joined on shutdown
+struct Threads {
+    deck: JoinHandle<()>,
+    output: Option<JoinHandle<()>>,
+}
+
+// A reply dropped unanswered. Once the notifier is closed the Deck is gone,
+// otherwise it panicked on the message and carried on, so it is worth asking again.
+fn unanswered(notifier: &Notifier) -> TE {
+    if notifier.is_closed() {
+        TE::ShutDown
+    } else {
+        TE::ChanRecvTimeout(RecvTimeoutError::Disconnected)
+    }
+}
+
+// Calls made to the DJ while it inits
 #[derive(Debug)]
 pub enum DM2DJ {
-    ID(Result<i32>),
     ARM(Arm),
+    Archive(Archive),
+    Notifier(Arc<Notifier>),
+    Vitals(Arc<Vitals>),
 }
 
 
 impl TheDJ {
 
     // Init with or without output reporting
-    pub fn init()                -> Result<TheDJ> { Self::init_(false) }
-    pub fn init_with_reporting() -> Result<TheDJ> { Self::init_(true) }
+    pub fn init()                -> Result<TheDJ> { Self::builder().build() }
+    pub fn init_with_reporting() -> Result<TheDJ> { Self::builder().reporting(true).build() }
 
-    fn init_(should_report: bool) -> Result<TheDJ> {
+    // Init with a clock other than the system clock, such as a MockClock
+    pub fn init_with_clock(clock: SharedClock, should_report: bool) -> Result<TheDJ> {
+        Self::builder().clock(clock).reporting(should_report).build()
+    }
+
+    // Configure the DJ and its runtimes before init, see Config
+    pub fn builder() -> TheDJBuilder {
+        TheDJBuilder::default()
+    }
 
-        // Create the channelS that connects the threads
+    pub(crate) fn init_(builder: TheDJBuilder) -> Result<TheDJ> {
+        let TheDJBuilder { config, clock, reports, reporting } = builder;
+        let should_report = reporting;
+
+        // Create the channelS that connects the threads. Senders to the Deck
+        // wait once <channel_bound> messages are queued.
         let (dj_tx, dj_rx) = mpsc::channel();
-        let (deck_tx, deck_rx) = mpsc::channel();  
-        let deck_tx_2 = deck_tx.clone();
+        let (deck_tx, deck_rx) = mpsc::sync_channel(config.channel_bound);  
         let (outputrunner_tx, outputrunner_rx) = mpsc::channel();  
 
         // Spin up the Deck, where the core data is stored/processed
-        Deck::run(deck_rx, dj_tx, outputrunner_tx.clone());
+        let deck = Deck::run_with(deck_rx, dj_tx, outputrunner_tx.clone(), clock.clone(), config);
 
         // Init the DJ 
         let mut the_dj = TheDJ { 
-            rt_tx: deck_tx_2.clone(),
-            rt_rx: dj_rx,
+            rt_tx: deck_tx,
             outputrunner_tx,
             atomic_record_map: None,
+            archive: None,
+            notifier: None,
+            vitals: None,
+            reporting: should_report,
+            clock: clock.clone(),
+            reply_timeout: config.reply_timeout,
+            stall: (config.deck_stall(), config.output_stall()),
+            threads: Arc::new(Mutex::new(None)),
         };
 
         // Get the new DJ a rwlock read only link of the atomic record map
         if let Err(e) = the_dj.rt_tx.send(DM2Deck::Init()) {
             return Err(TE::DM2DeckSendFail(e));
         } else {
-            match the_dj.rt_rx.recv() {
+            match dj_rx.recv_timeout(the_dj.reply_timeout) {
                 Ok(DM2DJ::ARM(arm)) => {
                     let arm_ = Some(arm.clone());
                     the_dj.atomic_record_map = arm_
                 },
-                Err(e) => return Err(TE::ChannelRecvFail(e)),
+                Err(e) => return Err(TE::ChanRecvTimeout(e)),
                 _ => return Err(TE::MaximumConfusion),
             }; 
+            match dj_rx.recv_timeout(the_dj.reply_timeout) {
+                Ok(DM2DJ::Archive(archive)) => the_dj.archive = Some(archive),
+                Err(e) => return Err(TE::ChanRecvTimeout(e)),
+                _ => return Err(TE::MaximumConfusion),
+            };
+            match dj_rx.recv_timeout(the_dj.reply_timeout) {
+                Ok(DM2DJ::Notifier(notifier)) => the_dj.notifier = Some(notifier),
+                Err(e) => return Err(TE::ChanRecvTimeout(e)),
+                _ => return Err(TE::MaximumConfusion),
+            };
+            match dj_rx.recv_timeout(the_dj.reply_timeout) {
+                Ok(DM2DJ::Vitals(vitals)) => the_dj.vitals = Some(vitals),
+                Err(e) => return Err(TE::ChanRecvTimeout(e)),
+                _ => return Err(TE::MaximumConfusion),
+            };
         }
 
         // If reporting, init the output runtime
+        let mut output = None;
         if should_report {
             let arm_ = the_dj.atomic_record_map.clone().expect("ARM not initialized");
-            thread::spawn(move  || {
+            let archive = the_dj.archive.clone().expect("Archive not initialized");
+            let vitals = the_dj.vitals.clone().expect("Vitals not initialized");
+            output = Some(thread::Builder::new().name(String::from("thumper-output")).spawn(move  || {
                 let output_runner = Output {
                     atomic_record_map:arm_, 
                     // rt_tx: deck_tx.clone(),
                     outputrunner_rx: outputrunner_rx, 
+                    archive,
+                    clock,
+                    vitals,
+                    tick: config.output_tick,
                 };
                 output_runner.run();
-            });
+            }).expect("Could not spawn the Output"));
+        }
+        if let Ok(mut threads) = the_dj.threads.lock() {
+            *threads = Some(Threads { deck, output });
+        }
+        for report in reports {
+            the_dj.add_report(report)?;
         }
-
-        // This will tell the deck to update the atomic record map every 1 second
-        thread::spawn(move || {
-            loop {
-                // if let Err(e) = deck_tx_2.send(DM2Deck::UpdateAtomicRecordMap) {
-                //     panic!("Could not send reqwuest to update: {:?}", e);
-                // }
-                thread::sleep(Duration::from_secs(1));
-            }
-        });
 
         // Return the instance of TheDJ  to caller
         Ok(the_dj)
@@ -106,59 +178,417 @@ impl TheDJ {
 
     // Add a record to the record map and return an assoiciated Beat struct
     pub fn spin_new(&self, name: String) -> Result<Beat> {
+        self.spin_new_(name, None)
+    }
+
+    // As spin_new, keeping <track_len> beats for this record rather than the
+    // track length the DJ was built with
+    pub fn spin_new_with_track_len(&self, name: String, track_len: usize) -> Result<Beat> {
+        self.spin_new_(name, Some(track_len))
+    }
+
+    fn spin_new_(&self, name: String, track_len: Option<usize>) -> Result<Beat> {
 
         // Verify input data
         if name.len() == 0 {
             return Err(TE::RegisterFail ("Error: Incorrect register data"))
         }
+        if track_len == Some(0) {
+            return Err(TE::RegisterFail ("Error: Track length must be above zero"))
+        }
 
         // Make a registration call and create a new Beat with the returned id
         // and a cloned copy of the runtime call sender. For pings.
-        if let Err(e) = self.rt_tx.send(DM2Deck::Registration(name)) {
-            Err(TE::DM2DeckSendFail(e))
+        // The reply comes back on a channel of its own. Should it not arrive in
+        // time the Deck rolls the registration back once it finds nobody waiting.
+        let ring = Arc::new(BeatRing::new(RING_CAP));
+        let (reply_tx, reply_rx) = mpsc::channel();
+        if self.rt_tx.send(DM2Deck::Registration(name, ring.clone(), track_len, reply_tx)).is_err() {
+            Err(TE::ShutDown)
         } else {
-            // WARNING: What if the deck never returns a response?
-            // TODO: Timeout?
-            match self.rt_rx.recv() {
-                Ok(DM2DJ::ID(Ok(id))) => {
-                    Ok(Beat{id, sender: self.rt_tx.clone()})
+            match reply_rx.recv_timeout(self.reply_timeout) {
+                Ok(Ok(handle)) => Ok(self.new_beat(handle, ring)),
+                Ok(Err(e)) => Err(e),
+                Err(RecvTimeoutError::Disconnected) => Err(unanswered(&self.notifier())),
+                Err(e) => {
+                    self.abandon_registration(reply_rx);
+                    Err(TE::ChanRecvTimeout(e))
                 },
-                Ok(DM2DJ::ID(Err(e))) => Err(e),
-                Err(e) => Err(TE::ChannelRecvFail(e)),
-                _ => Err(TE::MaximumConfusion),
             } 
         }
     }
 
-    // Remove a record from the record map
+    // The reply may have come in since giving up on it, in which case the Deck
+    // doesn't know nobody is waiting. Remove the record it registered.
+    fn abandon_registration(&self, reply_rx: mpsc::Receiver<Result<Handle>>) {
+        if let Ok(Ok(handle)) = reply_rx.try_recv() {
+            let _ = self.rt_tx.send(DM2Deck::Deregistration(handle));
+        }
+    }
+
+    // As spin_new, without blocking the thread. Safe to await on any executor.
+    pub async fn spin_new_async(&self, name: String) -> Result<Beat> {
+        self.spin_new_async_(name, None).await
+    }
+
+    // As spin_new_with_track_len, without blocking the thread
+    pub async fn spin_new_async_with_track_len(&self, name: String, track_len: usize) -> Result<Beat> {
+        self.spin_new_async_(name, Some(track_len)).await
+    }
+
+    async fn spin_new_async_(&self, name: String, track_len: Option<usize>) -> Result<Beat> {
+        if name.is_empty() {
+            return Err(TE::RegisterFail ("Error: Incorrect register data"))
+        }
+        if track_len == Some(0) {
+            return Err(TE::RegisterFail ("Error: Track length must be above zero"))
+        }
+        let ring = Arc::new(BeatRing::new(RING_CAP));
+        let (reply_tx, reply_rx) = mpsc::channel();
+        if self.rt_tx.send(DM2Deck::Registration(name, ring.clone(), track_len, reply_tx)).is_err() {
+            return Err(TE::ShutDown)
+        }
+        // The reply is checked for once more at the deadline, and the channel
+        // dropped with the future, so the Deck rolls back what comes in later
+        let deadline = Instant::now() + self.reply_timeout;
+        let notifier = self.notifier();
+        let reply = Until::new(self.notifier(), Some(deadline), move || match reply_rx.try_recv() {
+            Ok(res) => Some(res),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(unanswered(&notifier))),
+        }).await;
+        match reply {
+            Some(Ok(handle)) => Ok(self.new_beat(handle, ring)),
+            Some(Err(e)) => Err(e),
+            None if self.notifier().is_closed() => Err(TE::ShutDown),
+            None => Err(TE::ChanRecvTimeout(RecvTimeoutError::Timeout)),
+        }
+    }
+
+    fn new_beat(&self, handle: Handle, ring: Arc<BeatRing>) -> Beat {
+        Beat{
+            id: handle.id,
+            generation: handle.generation,
+            sender: self.rt_tx.clone(),
+            clock: self.clock.clone(),
+            ring,
+            reply_timeout: self.reply_timeout,
+            completed: false,
+        }
+    }
+
+    fn notifier(&self) -> Arc<Notifier> {
+        self.notifier.clone().expect("You have no Notifier here")
+    }
+
+    // Have the Deck evaluate the health of every record and the Output run the
+    // reports that are due, without waiting for their next tick. Both go by the
+    // clock, so after advancing a MockClock this brings everything up to date
+    // before returning.
+    pub fn tick(&self) -> Result<()> {
+        let deadline = Instant::now() + self.reply_timeout;
+        let remaining = || deadline.saturating_duration_since(Instant::now());
+
+        let (tx, rx) = mpsc::channel();
+        self.rt_tx.send(DM2Deck::Tick(tx)).map_err(|_| TE::ShutDown)?;
+        rx.recv_timeout(remaining())??;
+
+        if !self.reporting { return Ok(()) };
+        let (tx, rx) = mpsc::channel();
+        self.outputrunner_tx.send(DM2OutputRunner::TickOutput(tx))?;
+        rx.recv_timeout(remaining())?
+    }
+
+    // Stop the runtime. The Deck stops taking beats, handles the messages already
+    // queued and brings the ARM up to date. The reports then run a last time and
+    // are ended, and the threads joined. Every clone of the DJ is shut down, and
+    // only the first call does anything. A thread that doesn't stop within the
+    // timeout is left behind with an error.
+    pub fn shutdown(&self, timeout: Duration) -> Result<()> {
+        let threads = match self.threads.lock() {
+            Ok(mut threads) => threads.take().ok_or(TE::ShutDown)?,
+            Err(_) => return Err(TE::MaximumConfusion),
+        };
+        let deadline = Instant::now() + timeout;
+        let remaining = || deadline.saturating_duration_since(Instant::now());
+        let join = |thread: JoinHandle<()>, name| thread.join().map_err(|_| TE::ThreadPanicked(name));
+
+        // The Deck goes first so the last report has the last beats. Its queue
+        // may be full, that is waited out no longer than the timeout.
+        let (tx, rx) = mpsc::channel();
+        let mut msg = DM2Deck::Shutdown(tx);
+        let sent = loop {
+            match self.rt_tx.try_send(msg) {
+                Ok(_) => break Ok(()),
+                Err(TrySendError::Full(_)) if remaining().is_zero() => break Err(None),
+                Err(TrySendError::Full(m)) => {
+                    msg = m;
+                    thread::sleep(remaining().min(DRAIN_TICK));
+                },
+                Err(TrySendError::Disconnected(m)) => break Err(Some(SendError(m))),
+            }
+        };
+        let deck = match sent {
+            Ok(_) => match rx.recv_timeout(remaining()) {
+                Ok(res) => join(threads.deck, "deck").and(res),
+                Err(RecvTimeoutError::Disconnected) => join(threads.deck, "deck").and(Err(TE::ShutDown)),
+                Err(e) => Err(TE::ChanRecvTimeout(e)),
+            },
+            Err(None) => Err(TE::ChanRecvTimeout(RecvTimeoutError::Timeout)),
+            // Not there to take the message, it can only have panicked
+            Err(Some(e)) => join(threads.deck, "deck").and(Err(TE::DM2DeckSendFail(e))),
+        };
+
+        let output = match threads.output {
+            Some(output) => {
+                let (tx, rx) = mpsc::channel();
+                match self.outputrunner_tx.send(DM2OutputRunner::StopOutput(tx)) {
+                    Ok(_) => match rx.recv_timeout(remaining()) {
+                        Ok(res) => join(output, "output").and(res),
+                        Err(RecvTimeoutError::Disconnected) => join(output, "output").and(Err(TE::ShutDown)),
+                        Err(e) => Err(TE::ChanRecvTimeout(e)),
+                    },
+                    Err(e) => join(output, "output").and(Err(TE::DM2OutputRunner(e))),
+                }
+            },
+            None => Ok(()),
+        };
+        deck.and(output)
+    }
+
+    // How the runtimes themselves are doing. A runtime that panicked has been
+    // restarted and counted, one that hasn't gone round its loop for longer than
+    // STALL_THRESHOLD, or a couple of its ticks, is Stalled, and a thread that
+    // ended on its own is Dead.
+    pub fn runtime_health(&self) -> Result<RuntimeHealth> {
+        let vitals = self.vitals.as_ref().expect("You have no Vitals here");
+        let threads = self.threads.lock().map_err(|_| TE::MaximumConfusion)?;
+        let state = |thread: Option<&JoinHandle<()>>, silence: Duration, stall: Duration| match thread {
+            None => RuntimeState::Stopped,
+            Some(t) if t.is_finished() => RuntimeState::Dead,
+            Some(_) if silence > stall => RuntimeState::Stalled(silence),
+            Some(_) => RuntimeState::Running,
+        };
+        let deck = state(threads.as_ref().map(|t| &t.deck), vitals.deck_silence(), self.stall.0);
+        let output = match self.reporting {
+            true => Some(state(threads.as_ref().and_then(|t| t.output.as_ref()), vitals.output_silence(), self.stall.1)),
+            false => None,
+        };
+        Ok(RuntimeHealth::new(deck, output, vitals))
+    }
+
+    // How long to wait on the Deck for replies, such as the id of a new Beat.
+    // Beats spun up afterwards wait as long.
+    pub fn set_reply_timeout(&mut self, timeout: Duration) {
+        self.reply_timeout = timeout;
+    }
+
+    // Remove a record from the record map, into the archive
     pub fn unregister(&self, id: i32) -> Result<()> {
-        if let Err(e) = self.rt_tx.send(DM2Deck::Deregistration(id)) {
-            Err(TE::DM2DeckSendFail(e))
+        if self.rt_tx.send(DM2Deck::Deregistration(self.handle(id)?)).is_err() {
+            Err(TE::ShutDown)
         } else {Ok(())}
     }
     
+    // How long records are kept in a terminal state once their Beat is gone
+    pub fn set_retention(&self, retention: Duration) -> Result<()> {
+        if self.rt_tx.send(DM2Deck::SetRetention(retention)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
     // Clear all records of beats
     pub fn clear_all(&self) -> Result<()> {
         self.get_roster()?.iter().map(|id| {
-            if let Err(e) = self.rt_tx.send(DM2Deck::Deregistration(*id)) {
-                Err(TE::DM2DeckSendFail(e))
+            if self.rt_tx.send(DM2Deck::Deregistration(self.handle(*id)?)).is_err() {
+                Err(TE::ShutDown)
             } else { Ok(()) }
         }).collect::<Result<_>>()
     }
 
-    // TODO optimize
-    // Returns a single record 
+    // Returns the most recently archived record with the id. Ids are reused, so
+    // earlier records with the same id can be found through get_archive.
+    pub fn get_archived(&self, id: i32) -> Result<Archived> {
+        if let Ok(archive) = self.archive.as_ref().expect("You have no archive here").read() {
+            if let Some(archived) = archive.iter().rev().find(|a| a.record.id == id) {
+                return Ok(archived.clone());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Returns every archived record, oldest first
+    pub fn get_archive(&self) -> Result<Vec<Archived>> {
+        if let Ok(archive) = self.archive.as_ref().expect("You have no archive here").read() {
+            return Ok(archive.iter().cloned().collect());
+        }
+        Err(TE::MaximumConfusion)
+    }
+
+    // The handle of the current record with the id, for messaging the Deck
+    fn handle(&self, id: i32) -> Result<Handle> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.handle());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Returns a copy of a single record, including its track. To look at a
+    // record without copying it see get_record_arc or with_record.
     pub fn get_record(&self, id: i32) -> Result<Record> {
+        self.get_record_arc(id).map(|record| Record::clone(&record))
+    }
+
+    // Returns the current snapshot of a single record. It is shared with the
+    // Deck, so nothing is copied, and it won't change once handed out.
+    pub fn get_record_arc(&self, id: i32) -> Result<Arc<Record>> {
         if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
             if let Some(record) = record_map.get(&id) {
-                // return Ok(record.clone());
                 return Ok(record.clone());
             }
         }
         Err(TE::MissingRecord)
     }
+
+    // Run a query against a single record without copying it, such as
+    // `dj.with_record(id, |r| r.get_interval_stats())`. The ARM is not locked
+    // while the query runs.
+    pub fn with_record<T, F: FnOnce(&Record) -> T>(&self, id: i32, f: F) -> Result<T> {
+        self.get_record_arc(id).map(|record| f(&record))
+    }
+
+    // Run a query against every record without copying them, returning the
+    // results in no particular order
+    pub fn with_records<T, F: FnMut(&Record) -> T>(&self, mut f: F) -> Result<Vec<T>> {
+        let records = match self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            Ok(record_map) => record_map.values().cloned().collect::<Vec<Arc<Record>>>(),
+            Err(_) => return Err(TE::MaximumConfusion),
+        };
+        if records.is_empty() { return Err(TE::EmptyRoster) };
+        Ok(records.iter().map(|record| f(record)).collect())
+    }
+	
+    // Returns the health state of a record and when it last changed
+    pub fn get_health_state(&self, id: i32) -> Result<(HealthState, SystemTime)> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.get_health_state());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Set the enter/exit thresholds used by a record's health state machine
+    pub fn set_health_thresholds(&self, id: i32, thresholds: HealthThresholds) -> Result<()> {
+        thresholds.validate()?;
+        if self.rt_tx.send(DM2Deck::SetHealthThresholds(self.handle(id)?, thresholds)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Returns the share of a record's recent beats that reported success
+    pub fn get_success_ratio(&self, id: i32) -> Result<Option<f32>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.get_success_ratio());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Returns the most recent value of a field sent along with the beats of a record
+    pub fn get_field(&self, id: i32, name: &str) -> Result<Option<f64>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.get_field(name));
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Returns the expected freq of a record as set by the user, if it has been
+    pub fn get_user_freq(&self, id: i32) -> Result<Option<Duration>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.user_freq);
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Returns the expected freq of a record as inferred from its beats, along
+    // with the confidence in it
+    pub fn get_inferred_freq(&self, id: i32) -> Result<Option<(Duration, ConfidenceLevel)>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.inferred_freq.clone());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Set the grace period after deployment during which a record is not rated
+    // or reported on
+    pub fn set_warmup(&self, id: i32, warmup: Duration) -> Result<()> {
+        if self.rt_tx.send(DM2Deck::SetWarmup(self.handle(id)?, warmup)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Set the band around the expected freq that a record is rated optimal within
+    pub fn set_tolerance(&self, id: i32, tolerance: Tolerance) -> Result<()> {
+        tolerance.validate()?;
+        if self.rt_tx.send(DM2Deck::SetTolerance(self.handle(id)?, tolerance)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Expect a beat within `grace` of every run of a cron style schedule
+    pub fn set_schedule(&self, id: i32, schedule: &str, grace: Duration) -> Result<()> {
+        let schedule = Schedule::parse(schedule)?;
+        if self.rt_tx.send(DM2Deck::SetSchedule(self.handle(id)?, schedule, grace)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Returns the scheduled runs of a record that never beat
+    pub fn get_missed_runs(&self, id: i32) -> Result<Vec<SystemTime>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.get_missed_runs());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
+
+    // Set how long after its most recent beat a record is considered overdue
+    pub fn set_deadline(&self, id: i32, deadline: Deadline) -> Result<()> {
+        deadline.validate()?;
+        if self.rt_tx.send(DM2Deck::SetDeadline(self.handle(id)?, deadline)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Beats sent from a timestamp older than this are rejected, None for no limit
+    pub fn set_max_beat_age(&self, id: i32, max_age: Option<Duration>) -> Result<()> {
+        if self.rt_tx.send(DM2Deck::SetMaxBeatAge(self.handle(id)?, max_age)).is_err() {
+            Err(TE::ShutDown)
+        } else {Ok(())}
+    }
+
+    // Returns the overdue details of a record, if it is past its deadline
+    pub fn get_overdue(&self, id: i32) -> Result<Option<Overdue>> {
+        if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() {
+            if let Some(record) = record_map.get(&id) {
+                return Ok(record.get_overdue());
+            }
+        }
+        Err(TE::MissingRecord)
+    }
 	
-    // Returns a list of record ids
+    // Returns a list of record ids, including the terminal records still kept
     pub fn get_roster(&self) -> Result<Vec<i32>> {
         if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() { 
             let mut roster = Vec::new();
@@ -171,12 +601,12 @@ impl TheDJ {
         Err(TE::MaximumConfusion)
     }
 
-    // Returns a count struct of records in the roster
+    // Returns the ids of the records that have beaten and whose task isn't gone
     pub fn get_roster_actives(&self) -> Result<Vec<i32>> {
         if let Ok(record_map) = self.atomic_record_map.as_ref().expect("You have no ARM here").read() { 
             let mut roster = Vec::new();
             record_map.values()
-                .filter(|x| x.raw_track.back().is_some()) 
+                .filter(|x| x.raw_track.back().is_some() && !x.is_terminated())
                 .for_each(|x| roster.push(x.id));
             if !roster.is_empty() {
                 return Ok(roster)
@@ -195,20 +625,43 @@ impl TheDJ {
     }
 
     // Blocking the thread that the DJ is in until beat counts are up to a certain
-    // amount or a certain wait time has been reached
+    // amount or a certain wait time has been reached. This waits on the runtime
+    // thread, so the timeout is in real time regardless of the DJ's clock.
     pub fn block_for_beats(&self, count: usize, timeout: Duration) -> Result<()> {
-        let mut running_count = 0;
-        let start_time = SystemTime::now();
-        loop {
-            if SystemTime::now().duration_since(start_time)? >= timeout { return Err(TE::NothingNewToReport)};
+        block_on(self.wait_for_beats(count, timeout), Some(Instant::now() + timeout))
+    }
 
-            if let Ok(roster) = self.get_roster_actives() {
-                if roster.len() >= running_count { running_count = roster.len()};
-            }
-            if running_count >= count { return Ok(()) };
+    // As block_for_beats, without blocking the thread. The roster is checked
+    // again whenever the Deck updates it rather than on an interval.
+    pub async fn wait_for_beats(&self, count: usize, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        let roster = || self.get_roster_actives().map_or(0, |r| r.len());
+        match Until::new(self.notifier(), Some(deadline), || (roster() >= count).then_some(())).await {
+            Some(()) => Ok(()),
+            None => Err(self.gave_up()),
+        }
+    }
 
-            thread::sleep(Duration::from_millis(250));            
+    // Wait until a record enters the given health state, returning when it did.
+    // Also real time, regardless of the DJ's clock.
+    pub async fn wait_for_state(&self, id: i32, state: HealthState, timeout: Duration) -> Result<SystemTime> {
+        let deadline = Instant::now() + timeout;
+        let check = || match self.get_health_state(id) {
+            Ok((current, since)) if current == state => Some(Ok(since)),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        };
+        match Until::new(self.notifier(), Some(deadline), check).await {
+            Some(res) => res,
+            None => Err(self.gave_up()),
+        }
+    }
 
+    // Why a wait on the Deck came back empty handed
+    fn gave_up(&self) -> TE {
+        match self.notifier().is_closed() {
+            true => TE::ShutDown,
+            false => TE::NothingNewToReport,
         }
     }
 }
\ No newline at end of file
@@ -5,9 +5,25 @@ mod deck;
 mod record;
 mod track;
 mod beat;
+mod stats;
+mod schedule;
+mod clock;
+mod archive;
+mod ring;
+mod notify;
+mod vitals;
+mod builder;
 
 pub use dj::*;
 pub use deck::*;
 pub use record::*;
 pub use track::*;
-pub use beat::*;
\ No newline at end of file
+pub use beat::*;
+pub use stats::*;
+pub use schedule::*;
+pub use clock::*;
+pub use archive::*;
+pub use ring::*;
+pub use notify::*;
+pub use vitals::*;
+pub use builder::*;
\ No newline at end of file
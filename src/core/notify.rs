@@ -0,0 +1,136 @@
+use std::time::Instant;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::future::Future;
+use std::pin::{Pin, pin};
+use std::task::{Context, Poll, Waker, Wake};
+use std::thread::{self, Thread};
+
+// ////////////////////////////////////////////////////////////////////////
+// Notifier
+// /////////////////////////////////////////////////////////////
+
+// Lets futures wait on the Deck without polling or a runtime of their own. A
+// future that can't finish yet leaves its waker here, and the Deck wakes every
+// waker once the ARM has been updated or a reply sent. Timeouts are kept here
+// too, the Deck wakes those that passed their deadline every DRAIN_TICK. Once
+// the Deck has stopped the notifier is closed, and nothing waits on it any more.
+#[derive(Default)]
+pub struct Notifier {
+    waiting: Mutex<Vec<(Option<Instant>, Waker)>>,
+    closed: AtomicBool,
+}
+
+impl Notifier {
+
+    pub fn new() -> Self { Notifier::default() }
+
+    // Wake the waker on the next change, or once the deadline has passed
+    pub fn register(&self, waker: &Waker, deadline: Option<Instant>) {
+        if let Ok(mut waiting) = self.waiting.lock() {
+            if let Some(w) = waiting.iter_mut().find(|(_, w)| w.will_wake(waker)) {
+                w.0 = deadline;
+            } else {
+                waiting.push((deadline, waker.clone()));
+            }
+        }
+    }
+
+    // Something changed, wake everything waiting
+    pub fn notify(&self) {
+        let waiting = match self.waiting.lock() {
+            Ok(mut waiting) => std::mem::take(&mut *waiting),
+            Err(_) => return,
+        };
+        waiting.into_iter().for_each(|(_, w)| w.wake());
+    }
+
+    // Nothing is going to change any more, wake everything waiting so it can
+    // give up
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+        self.notify();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    // Wake only those past their deadline
+    pub fn expire(&self, now: Instant) {
+        let expired = match self.waiting.lock() {
+            Ok(mut waiting) => {
+                let (expired, kept) = std::mem::take(&mut *waiting).into_iter()
+                    .partition::<Vec<_>, _>(|(d, _)| d.is_some_and(|d| d <= now));
+                *waiting = kept;
+                expired
+            },
+            Err(_) => return,
+        };
+        expired.into_iter().for_each(|(_, w)| w.wake());
+    }
+}
+
+impl std::fmt::Debug for Notifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let waiting = self.waiting.lock().map_or(0, |w| w.len());
+        write!(f, "Notifier {{ waiting: {}, closed: {} }}", waiting, self.is_closed())
+    }
+}
+
+// A future that is ready once `check` returns something, or with None once the
+// deadline has passed or the notifier is closed. It is checked again each time
+// the Deck wakes it, and the deadline each time it is polled.
+pub(crate) struct Until<T, F: FnMut() -> Option<T>> {
+    notifier: Arc<Notifier>,
+    deadline: Option<Instant>,
+    check: F,
+}
+
+impl<T, F: FnMut() -> Option<T>> Until<T, F> {
+    pub(crate) fn new(notifier: Arc<Notifier>, deadline: Option<Instant>, check: F) -> Self {
+        Until { notifier, deadline, check }
+    }
+}
+
+impl<T, F: FnMut() -> Option<T> + Unpin> Future for Until<T, F> {
+    type Output = Option<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        if let Some(done) = (self.check)() { return Poll::Ready(Some(done)) };
+        if self.notifier.is_closed() || self.deadline.is_some_and(|d| d <= Instant::now()) {
+            return Poll::Ready(None)
+        };
+        self.notifier.register(cx.waker(), self.deadline);
+
+        // The Deck may have notified, or stopped, between the check and registering
+        match (self.check)() {
+            Some(done) => Poll::Ready(Some(done)),
+            None if self.notifier.is_closed() => Poll::Ready(None),
+            None => Poll::Pending,
+        }
+    }
+}
+
+// Wakes a thread parked in block_on
+struct Unpark(Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) { self.0.unpark() }
+}
+
+// Run a future on this thread. Parked until woken, but never past the deadline,
+// so a future that enforces its own deadline returns in time whether or not the
+// Deck is still there to wake it.
+pub(crate) fn block_on<F: Future>(future: F, deadline: Option<Instant>) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        if let Poll::Ready(done) = future.as_mut().poll(&mut cx) { return done };
+        match deadline {
+            Some(d) => thread::park_timeout(d.saturating_duration_since(Instant::now())),
+            None => thread::park(),
+        }
+    }
+}
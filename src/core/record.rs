@@ -1,10 +1,11 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::iter::{Sum, ExactSizeIterator, Iterator};
 // use std::
 use itertools::Itertools;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use crate::{Track, Result, BEAT_CAP, LinearExt, LinearBeat};
+use crate::{Track, Result, TE, BEAT_CAP, OUTCOME_WINDOW, FAILING_RATIO, EWMA_ALPHA, LinearExt, LinearBeat, PatternExt, PatternBeat, IntervalStats, Schedule};
+use crate::{SharedClock, SystemClock, Stamp, Handle, CLOCK_JUMP_THRESHOLD};
 
 // ////////////////////////////////////////////////////////////////////////
 // Record
@@ -22,10 +23,329 @@ use crate::{Track, Result, BEAT_CAP, LinearExt, LinearBeat};
 // Enumerator to indicate activity level of the record
 #[derive(PartialEq, Debug)]
 pub enum ActivityRating {
-    Optimal,    // Within 2% of expected turn around
-    NotOptimal, // Beyond 2% difference in expected turn around
+    Optimal,    // Within tolerance of expected turn around
+    NotOptimal, // Beyond tolerance of expected turn around
     OnlyOnce,   // Only one beat in records
     NotOnce,    // No beats
+    Missing,    // Last beat is past its deadline, or a scheduled run never beat
+    WarmingUp,  // Within the warm-up window after deployment
+    Degraded,   // On time, but the latest beat reported a problem
+    Failing,    // On time, but most of the recent beats reported failure
+}
+
+// What the task reported along with a beat
+#[derive(Clone, Debug, PartialEq)]
+pub enum Outcome {
+    Success,
+    Degraded(String),   // Working, but not as it should, with a reason
+    Failed(String),     // The iteration failed, with a reason
+}
+
+// Which way a turn around is better to stray from the expected freq
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum BetterTo {
+    GoOver,
+    GoUnder,
+}
+
+// How sure the record is of the freq it infers from its beats
+#[derive(PartialEq, Debug, Clone)]
+pub enum ConfidenceLevel {
+    Very,        // Most confident
+    Likely,      //
+    Maybe,       //
+    Nil,         // Least confident
+    UserDefined, // Set by the user, takes precedence over the others
+}
+
+impl ConfidenceLevel {
+    // Sure enough to hold the record to a deadline, Likely or better
+    pub fn is_confident(&self) -> bool {
+        matches!(self, ConfidenceLevel::Very | ConfidenceLevel::Likely | ConfidenceLevel::UserDefined)
+    }
+}
+
+// A margin either side of the expected freq
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Margin {
+    Percent(f32),       // A percentage of the expected freq
+    Absolute(Duration), // A fixed duration
+}
+
+impl Margin {
+    pub fn of(&self, freq: Duration) -> Duration {
+        match self {
+            Margin::Percent(p) => {
+                let margin = freq.as_secs_f32() * p / 100.0;
+                Duration::try_from_secs_f32(margin).unwrap_or(if margin > 0.0 { Duration::MAX } else { Duration::ZERO })
+            },
+            Margin::Absolute(d) => *d,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Margin::Percent(p) if !p.is_finite() || *p < 0.0 => {
+                Err(TE::InvalidSetting("A percent margin must be finite and not negative"))
+            },
+            _ => Ok(()),
+        }
+    }
+}
+
+// The band around the expected freq that is considered optimal. The early and
+// late margins are kept separate so that being late can matter more than being
+// early, or vice versa.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    pub early: Margin,  // How much sooner than expected a turn around may be
+    pub late: Margin,   // How much later than expected a turn around may be
+}
+
+impl Default for Tolerance {
+    fn default() -> Self { Tolerance::percent(2.0) }
+}
+
+impl Tolerance {
+    pub fn percent(percent: f32) -> Self {
+        Tolerance { early: Margin::Percent(percent), late: Margin::Percent(percent) }
+    }
+
+    pub fn absolute(margin: Duration) -> Self {
+        Tolerance { early: Margin::Absolute(margin), late: Margin::Absolute(margin) }
+    }
+
+    // Asymmetric band, the loose margin is applied in the direction it is better to go
+    pub fn lenient(better_to: BetterTo, tight: Margin, loose: Margin) -> Self {
+        match better_to {
+            BetterTo::GoOver => Tolerance { early: tight, late: loose },
+            BetterTo::GoUnder => Tolerance { early: loose, late: tight },
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        self.early.validate().and(self.late.validate())
+    }
+
+    // Whether an actual turn around is within the band around the expected one
+    pub fn contains(&self, actual: Duration, expected: Duration) -> bool {
+        let start = expected.checked_sub(self.early.of(expected)).unwrap_or_default();
+        let end = expected.saturating_add(self.late.of(expected));
+        (start..=end).contains(&actual)
+    }
+}
+
+// When a beat is considered overdue, measured from the most recent beat
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Deadline {
+    FreqMultiple(f32),  // A multiple of the expected freq
+    Absolute(Duration), // A fixed duration, does not need an expected freq
+}
+
+impl Default for Deadline {
+    fn default() -> Self { Deadline::FreqMultiple(2.0) }
+}
+
+impl Deadline {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Deadline::FreqMultiple(m) if !m.is_finite() || *m <= 0.0 => {
+                Err(TE::InvalidSetting("A deadline's freq multiple must be finite and above zero"))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    // The deadline for an expected duration, None if it can't be worked out
+    fn of(&self, expected: Duration) -> Option<Duration> {
+        match self {
+            Deadline::FreqMultiple(m) => Duration::try_from_secs_f32(expected.as_secs_f32() * m).ok(),
+            Deadline::Absolute(d) => Some(*d),
+        }
+    }
+}
+
+// Calendar expectations of a record, for tasks that run on a schedule rather
+// than at a fixed freq. Every scheduled run is expected to beat within the
+// grace window either side of it.
+#[derive(Clone, Debug)]
+pub struct Scheduled {
+    pub schedule: Schedule,
+    pub grace: Duration,
+    pub next_run: Option<SystemTime>,   // Next run that has yet to be checked
+    pub missed: VecDeque<SystemTime>,   // Most recent <BEAT_CAP> runs that never beat
+    pub last_missed: bool,              // Whether the most recently checked run was missed
+}
+
+// Details of a record whose last beat has passed its deadline
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Overdue {
+    pub since_last: Duration,   // Time elapsed since the most recent beat
+    pub missed: u32,            // Number of expected beats that never arrived
+}
+
+// Enumerator of the record's health. Unlike the ActivityRating, which is
+// recalculated from scratch, this is kept by the Deck between evaluations and
+// only moves once an enter/exit threshold has been crossed.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum HealthState {
+    Healthy,    // Beating as expected
+    Late,       // Turn around has drifted past the late threshold
+    Missing,    // Turn around has drifted past the missing threshold
+    Dead,       // Turn around has drifted past the dead threshold
+    Recovered,  // Back under an exit threshold, waiting to settle into Healthy
+    Completed,  // Terminal, the task called Beat::complete
+    Panicked,   // Terminal, the Beat was dropped while its thread panicked
+    Abandoned,  // Terminal, the Beat was dropped without completing
+    Unregistered, // Terminal, removed through TheDJ
+}
+
+impl HealthState {
+    // Used to order the degraded states, Recovered counts as healthy here
+    fn severity(&self) -> u8 {
+        match self {
+            HealthState::Healthy | HealthState::Recovered | HealthState::Completed | HealthState::Unregistered => 0,
+            HealthState::Late => 1,
+            HealthState::Missing | HealthState::Abandoned => 2,
+            HealthState::Dead | HealthState::Panicked => 3,
+        }
+    }
+
+    // The task behind the record is gone, the state will no longer change
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, HealthState::Completed | HealthState::Panicked | HealthState::Abandoned | HealthState::Unregistered)
+    }
+}
+
+// A pair of ratios of lateness relative to the expected freq. A state is
+// entered once the lateness reaches `enter` and left once it drops below `exit`.
+#[derive(Clone, Copy, Debug)]
+pub struct Threshold {
+    pub enter: f32,
+    pub exit: f32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HealthThresholds {
+    pub late: Threshold,
+    pub missing: Threshold,
+    pub dead: Threshold,
+    pub settle: f32,    // Expected freqs to stay Recovered before Healthy
+}
+
+impl HealthThresholds {
+    // Finite ratios, each left no higher than it is entered
+    pub fn validate(&self) -> Result<()> {
+        for t in [self.late, self.missing, self.dead].iter() {
+            if !t.enter.is_finite() || !t.exit.is_finite() {
+                return Err(TE::InvalidSetting("Thresholds must be finite"))
+            }
+            if t.exit > t.enter {
+                return Err(TE::InvalidSetting("A threshold can't be left above where it is entered"))
+            }
+        }
+        if !self.settle.is_finite() || self.settle < 0.0 {
+            return Err(TE::InvalidSetting("Settle must be finite and not negative"))
+        }
+        Ok(())
+    }
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            late:    Threshold { enter: 0.10, exit: 0.05 },
+            missing: Threshold { enter: 1.0,  exit: 0.5 },
+            dead:    Threshold { enter: 5.0,  exit: 2.5 },
+            settle: 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct HealthTransition {
+    pub from: HealthState,
+    pub to: HealthState,
+    pub at: SystemTime,
+}
+
+// The health state machine, owned by the record
+#[derive(Clone, Debug)]
+pub struct Health {
+    pub state: HealthState,
+    pub since: SystemTime,                        // When the state last changed
+    pub thresholds: HealthThresholds,
+    pub transitions: VecDeque<HealthTransition>,  // Most recent <BEAT_CAP> transitions
+}
+
+impl Health {
+    pub fn new(now: SystemTime) -> Self {
+        Health {
+            state: HealthState::Healthy,
+            since: now,
+            thresholds: HealthThresholds::default(),
+            transitions: VecDeque::new(),
+        }
+    }
+
+    // Feed an observed lateness (ratio over the expected freq) into the machine,
+    // returning the transition if one was made
+    pub fn observe(&mut self, lateness: f32, freq: Duration, now: SystemTime) -> Option<HealthTransition> {
+        let t = &self.thresholds;
+
+        // The worst state that the lateness has entered
+        let entered = match lateness {
+            l if l >= t.dead.enter => HealthState::Dead,
+            l if l >= t.missing.enter => HealthState::Missing,
+            l if l >= t.late.enter => HealthState::Late,
+            _ => HealthState::Healthy,
+        };
+
+        let exit = match self.state {
+            HealthState::Late => t.late.exit,
+            HealthState::Missing => t.missing.exit,
+            HealthState::Dead => t.dead.exit,
+            _ => 0.0,
+        };
+
+        let next = if entered.severity() > self.state.severity() {
+            entered
+        } else if self.state.severity() > 0 && lateness < exit {
+            // Step down, but never straight into Healthy
+            match entered {
+                HealthState::Healthy => HealthState::Recovered,
+                n => n,
+            }
+        } else if self.state == HealthState::Recovered
+            && lateness < t.late.exit
+            && now.duration_since(self.since).unwrap_or_default()
+                >= Duration::try_from_secs_f32(freq.as_secs_f32() * t.settle).unwrap_or(Duration::MAX)
+        {
+            HealthState::Healthy
+        } else {
+            return None
+        };
+
+        self.transition(next, now)
+    }
+
+    // Move into a terminal state, after which observations are ignored
+    pub fn terminate(&mut self, state: HealthState, now: SystemTime) -> Option<HealthTransition> {
+        if self.state.is_terminal() { return None };
+        self.transition(state, now)
+    }
+
+    fn transition(&mut self, next: HealthState, now: SystemTime) -> Option<HealthTransition> {
+        if next == self.state { return None };
+        let transition = HealthTransition { from: self.state, to: next, at: now };
+        self.state = next;
+        self.since = now;
+        self.transitions.push_back(transition);
+        while self.transitions.len() > BEAT_CAP {
+            self.transitions.pop_front();
+        }
+        Some(transition)
+    }
 }
 
 
@@ -33,37 +353,241 @@ pub enum ActivityRating {
 pub struct Record {
     pub name: String,                 // Name is for reporting purposes
     pub id: i32,                      // Provided by the ID indexer
+    pub generation: u32,              // Times the id has been handed out before
     pub freq: Duration,               // Expected duration between beats
-    pub pattern: Vec<usize>,          // Best Expected pattern of the beats
+    pub user_freq: Option<Duration>,  // Expected freq as set by the user
+    pub inferred_freq: Option<(Duration, ConfidenceLevel)>, // Expected freq as observed
+    pub pattern: Vec<usize>,          // Best Expected pattern of the beats, as millis between them
     pub creation: SystemTime,         //
     pub deployment: SystemTime,       // Record's start time
-    pub raw_track: Track,             // Queue of of current <BEAT_CAP> beats
+    pub warmup: Duration,             // Grace period after deployment before rating
+    pub raw_track: Track,             // Queue of of current <track_len> beats
+    pub track_len: usize,             // How many beats, runs and field values are kept
     pub tuned_track: Track,           // A possibly manipulated copy of current raw_track  
+    pub health: Health,               // Health state kept between evaluations
+    pub deadline: Deadline,           // When the last beat is considered overdue
+    pub tolerance: Tolerance,         // Band around freq that is considered optimal
+    pub scheduled: Option<Scheduled>, // Calendar expectations, if any
+    pub clock: SharedClock,           // Source of time, shared with the DJ
+    pub out_of_order: u32,            // Beats that arrived before the previous beat
+    pub clock_jumps: u32,             // Times the wall clock was stepped between beats
+    pub lost_beats: u64,              // Beats overwritten in the Beat's ring before the Deck drained them
+    pub max_beat_age: Option<Duration>, // Oldest a beat sent from a timestamp may be
+    pub runs: VecDeque<Duration>,     // How long each of the last <track_len> spans of work took
+    pub open_span: Option<Stamp>,     // Start of the span of work currently running
+    pub fields: HashMap<String, VecDeque<(SystemTime, f64)>>, // Last <track_len> values sent with beats, per field
+    pub outcomes: VecDeque<(SystemTime, Outcome)>, // Last <OUTCOME_WINDOW> outcomes reported with beats
+    pub terminated: Option<Duration>, // When the record entered a terminal state, on the monotonic timeline
 }
 
 impl Record {
     pub fn new(name: String, id: i32) -> Self {
+        Self::new_with_clock(name, id, SystemClock::shared())
+    }
+
+    pub fn new_with_clock(name: String, id: i32, clock: SharedClock) -> Self {
+        let now = clock.now();
         Record {
             name,
             id,
-            creation: SystemTime::now(),
-            deployment: SystemTime::now(),
+            generation: 0,
+            creation: now,
+            deployment: now,
+            warmup: Duration::from_secs(0),
             pattern: Vec::new(),
             freq: Duration::from_secs(0),
+            user_freq: None,
+            inferred_freq: None,
             raw_track: Track(VecDeque::new()),
+            track_len: BEAT_CAP,
             tuned_track: Track(VecDeque::new()),
+            health: Health::new(now),
+            deadline: Deadline::default(),
+            tolerance: Tolerance::default(),
+            scheduled: None,
+            clock,
+            out_of_order: 0,
+            clock_jumps: 0,
+            lost_beats: 0,
+            max_beat_age: None,
+            runs: VecDeque::new(),
+            open_span: None,
+            fields: HashMap::new(),
+            outcomes: VecDeque::new(),
+            terminated: None,
+        }
+    }
+
+    pub fn handle(&self) -> Handle {
+        Handle { id: self.id, generation: self.generation }
+    }
+
+    pub fn add_beat(&mut self, time: SystemTime) -> Result<()> {
+        self.add_stamp(self.clock.stamp_at(time))
+    }
+
+    // Add a beat, noting when it arrives out of order or the wall clock has been
+    // stepped since the previous beat
+    pub fn add_stamp(&mut self, stamp: Stamp) -> Result<()> {
+        let (mut out_of_order, mut clock_jump) = (false, false);
+        if let Some(last) = self.raw_track.0.back() {
+            match stamp.mono.checked_sub(last.mono) {
+                None => out_of_order = true,
+                Some(mono) => {
+                    let drift = match stamp.wall.duration_since(last.wall) {
+                        Ok(wall) => wall.abs_diff(mono),
+                        Err(e) => mono + e.duration(),
+                    };
+                    clock_jump = drift > CLOCK_JUMP_THRESHOLD;
+                },
+            }
+        }
+        self.raw_track.add_capped(stamp, self.track_len)?;
+        if out_of_order { self.out_of_order += 1 };
+        if clock_jump { self.clock_jumps += 1 };
+        Ok(())
+    }
+
+    // Beats sent from a timestamp, rather than stamped as they happen, are checked
+    // before being added. A beat may not be from the future, give or take the
+    // skew the clock tolerates, nor older than the max beat age if one is set.
+    pub fn validate_stamp(&self, stamp: &Stamp) -> Result<()> {
+        let now = self.clock.monotonic();
+        if stamp.mono > now + CLOCK_JUMP_THRESHOLD {
+            return Err(TE::FutureBeat)
+        }
+        match self.max_beat_age {
+            Some(max) if now.checked_sub(stamp.mono).is_some_and(|age| age > max) => Err(TE::StaleBeat),
+            _ => Ok(()),
         }
     }
 
-    pub fn add_beat(&mut self, time: SystemTime) {
-        self.raw_track.add(time);
+    pub fn set_max_beat_age(&mut self, max_age: Option<Duration>) {
+        self.max_beat_age = max_age;
+    }
+
+    // How many beats, runs and field values to keep, dropping the oldest of
+    // those already kept if there are more
+    pub fn set_track_len(&mut self, track_len: usize) {
+        self.track_len = track_len;
+        while self.raw_track.0.len() > track_len { self.raw_track.0.pop_front(); }
+        while self.runs.len() > track_len { self.runs.pop_front(); }
+        for values in self.fields.values_mut() {
+            while values.len() > track_len { values.pop_front(); }
+        }
+    }
+
+    // Keep the numeric fields sent along with the beat at `wall`
+    pub fn add_fields(&mut self, wall: SystemTime, fields: Vec<(String, f64)>) {
+        for (name, value) in fields {
+            let values = self.fields.entry(name).or_default();
+            values.push_back((wall, value));
+            while values.len() > self.track_len {
+                values.pop_front();
+            }
+        }
+    }
+
+    // Keep the outcome reported along with the beat at `wall`
+    pub fn add_outcome(&mut self, wall: SystemTime, outcome: Outcome) {
+        self.outcomes.push_back((wall, outcome));
+        while self.outcomes.len() > OUTCOME_WINDOW {
+            self.outcomes.pop_front();
+        }
+    }
+
+    // Share of the outcomes in the window that were a success
+    pub fn get_success_ratio(&self) -> Option<f32> {
+        self.get_outcome_ratio(|o| *o == Outcome::Success)
+    }
+
+    // Share of the outcomes in the window that failed
+    pub fn get_failure_ratio(&self) -> Option<f32> {
+        self.get_outcome_ratio(|o| matches!(o, Outcome::Failed(_)))
+    }
+
+    fn get_outcome_ratio<F: Fn(&Outcome) -> bool>(&self, f: F) -> Option<f32> {
+        match self.outcomes.len() {
+            0 => None,
+            n => Some(self.outcomes.iter().filter(|(_, o)| f(o)).count() as f32 / n as f32),
+        }
+    }
+
+    // The most recent failure in the window and when it was reported
+    pub fn get_last_failure(&self) -> Option<(SystemTime, &str)> {
+        self.outcomes.iter().rev().find_map(|(w, o)| match o {
+            Outcome::Failed(reason) => Some((*w, reason.as_str())),
+            _ => None,
+        })
+    }
+
+    // Failing or Degraded when the task has been reporting problems, whether or
+    // not it is beating on time
+    fn get_outcome_rating(&self) -> Option<ActivityRating> {
+        if self.get_failure_ratio()? >= FAILING_RATIO { return Some(ActivityRating::Failing) };
+        match self.outcomes.back() {
+            Some((_, Outcome::Success)) | None => None,
+            _ => Some(ActivityRating::Degraded),
+        }
+    }
+
+    // Most recent value of a field
+    pub fn get_field(&self, name: &str) -> Option<f64> {
+        self.fields.get(name)?.back().map(|(_, v)| *v)
+    }
+
+    // Recent values of a field, oldest first
+    pub fn get_field_values(&self, name: &str) -> Option<Vec<f64>> {
+        self.fields.get(name).map(|values| values.iter().map(|(_, v)| *v).collect())
+    }
+
+    // The fields sent along with the beat at `wall`, sorted by name
+    pub fn get_fields_at(&self, wall: SystemTime) -> Vec<(&str, f64)> {
+        let mut fields = self.fields.iter()
+            .filter_map(|(name, values)| {
+                values.iter().rev().find(|(w, _)| *w == wall).map(|(_, v)| (name.as_str(), *v))
+            })
+            .collect::<Vec<(&str, f64)>>();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        fields
+    }
+
+    // A span of work has started. The start is also a beat, so the intervals
+    // between beats are the period of the loop and the runs are how much of it
+    // was spent working. A span left open is replaced by the new one.
+    pub fn start_span(&mut self, stamp: Stamp) -> Result<()> {
+        self.add_stamp(stamp)?;
+        self.open_span = Some(stamp);
+        Ok(())
+    }
+
+    // The span of work currently open has finished, a finish without a start is ignored
+    pub fn end_span(&mut self, stamp: Stamp) -> Option<Duration> {
+        let run = stamp.mono.checked_sub(self.open_span.take()?.mono)?;
+        self.runs.push_back(run);
+        while self.runs.len() > self.track_len {
+            self.runs.pop_front();
+        }
+        Some(run)
     }
 
     pub fn set_deployment(&mut self, deployment: SystemTime) {
         self.deployment = deployment;
     }
 
+    pub fn set_warmup(&mut self, warmup: Duration) {
+        self.warmup = warmup;
+    }
+
+    // Whether the record is still within its warm-up window after deployment
+    pub fn is_warming_up(&self) -> bool {
+        // A warmup too long to add to the deployment never ends
+        self.deployment.checked_add(self.warmup).is_none_or(|end| self.clock.now() < end)
+    }
+
+    // A user defined freq always takes precedence over an inferred one
     pub fn set_expected_freq(&mut self, expected: Duration) {
+        self.user_freq = Some(expected);
         self.freq = expected;
     }
 
@@ -71,13 +595,220 @@ impl Record {
         self.pattern = expected;
     }
 
+    // The expected pattern as durations between beats
+    pub fn get_pattern(&self) -> Vec<Duration> {
+        self.pattern.iter().map(|ms| Duration::from_millis(*ms as u64)).collect()
+    }
+
+    // Durations between beats paired with what the pattern expected them to be
+    pub fn get_pattern_beats(&self) -> Vec<PatternBeat> {
+        self.raw_track.into_iter().pattern(&self.get_pattern()).collect()
+    }
+
+    pub fn set_health_thresholds(&mut self, thresholds: HealthThresholds) {
+        self.health.thresholds = thresholds;
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: Tolerance) {
+        self.tolerance = tolerance;
+    }
+
+    // Expect a beat for every run of the schedule from now on
+    pub fn set_schedule(&mut self, schedule: Schedule, grace: Duration) {
+        let next_run = schedule.next_after(self.clock.now());
+        self.scheduled = Some(Scheduled {
+            schedule,
+            grace,
+            next_run,
+            missed: VecDeque::new(),
+            last_missed: false,
+        });
+    }
+
+    // Check every scheduled run whose grace window has closed for a beat within
+    // it, returns the number of runs newly flagged as missed
+    pub fn check_schedule(&mut self) -> usize {
+        if self.is_terminated() { return 0 };
+        let now = self.clock.now();
+        let track = &self.raw_track;
+        let scheduled = match self.scheduled.as_mut() {
+            Some(s) => s,
+            None => return 0,
+        };
+
+        let mut missed = 0;
+        while let Some(run) = scheduled.next_run {
+            if run + scheduled.grace > now { break };
+            let from = run.checked_sub(scheduled.grace).unwrap_or(run);
+            let to = run + scheduled.grace;
+            scheduled.last_missed = !track.into_iter().any(|b| (from..=to).contains(&b.wall));
+            if scheduled.last_missed {
+                scheduled.missed.push_back(run);
+                missed += 1;
+            }
+            scheduled.next_run = scheduled.schedule.next_after(run);
+        }
+        while scheduled.missed.len() > BEAT_CAP {
+            scheduled.missed.pop_front();
+        }
+        missed
+    }
+
+    // Scheduled runs that never beat
+    pub fn get_missed_runs(&self) -> Vec<SystemTime> {
+        match &self.scheduled {
+            Some(s) => s.missed.iter().copied().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    fn missed_last_run(&self) -> bool {
+        self.scheduled.as_ref().is_some_and(|s| s.last_missed)
+    }
+
+    pub fn set_deadline(&mut self, deadline: Deadline) {
+        self.deadline = deadline;
+    }
+
+    // Current health state and when it was entered
+    pub fn get_health_state(&self) -> (HealthState, SystemTime) {
+        (self.health.state, self.health.since)
+    }
+
+    // How far past the expected freq the average turn around is, as a ratio of
+    // the expected freq. Early beats are not considered late, nor are any while
+    // the freq is only a rough guess.
+    pub fn get_lateness(&self) -> Option<f32> {
+        if !self.pattern.is_empty() { return self.get_pattern_lateness() };
+        let freq = self.get_confident_freq()?;
+        if self.raw_track.len() < 2 { return None };
+        let avg = self.get_average()?;
+        let late = avg.checked_sub(freq).unwrap_or_default();
+        Some(late.as_secs_f32() / freq.as_secs_f32())
+    }
+
+    // As get_lateness, but comparing against the expected pattern rather than freq
+    fn get_pattern_lateness(&self) -> Option<f32> {
+        let pattern = self.get_pattern();
+        let mut iter = self.raw_track.into_iter().pattern(&pattern);
+        let (mut actual, mut expected) = (Duration::from_secs(0), Duration::from_secs(0));
+        for beat in &mut iter {
+            actual += beat.actual;
+            expected += beat.expected;
+        }
+        if expected.as_nanos() == 0 { return None };
+
+        // Count the time since the last beat once it runs over what is expected
+        if let (Some(next), Some(gap)) = (iter.expected_next(), self.raw_track.since_last(self.clock.monotonic())) {
+            if gap > next {
+                actual += gap;
+                expected += next;
+            }
+        }
+        let late = actual.checked_sub(expected).unwrap_or_default();
+        Some(late.as_secs_f32() / expected.as_secs_f32())
+    }
+
+    // Determine if the most recent beat is past its deadline. Unlike the
+    // average, this flags a record as soon as the deadline passes.
+    pub fn get_overdue(&self) -> Option<Overdue> {
+        let since_last = self.raw_track.since_last(self.clock.monotonic())?;
+        let freq = self.get_confident_freq();
+        let deadline = match self.deadline {
+            // A pattern's longest expected duration stands in for the freq
+            Deadline::FreqMultiple(_) if !self.pattern.is_empty() => {
+                self.deadline.of(self.get_pattern().into_iter().max()?)?
+            },
+            Deadline::FreqMultiple(_) => self.deadline.of(freq?)?,
+            Deadline::Absolute(d) => d,
+        };
+        if since_last <= deadline { return None };
+
+        // Without an expected freq the best we know is that one beat was missed
+        let missed = match freq {
+            None => 1,
+            Some(f) => (since_last.as_nanos() / f.as_nanos()) as u32,
+        };
+        Some(Overdue { since_last, missed })
+    }
+
+    // The expected freq, if it is sure enough to judge health and deadlines by.
+    // A few jittery intervals give an inferred freq too little confidence for that.
+    pub fn get_confident_freq(&self) -> Option<Duration> {
+        match self.freq.as_nanos() {
+            0 => None,
+            _ if self.get_freq_confidence().is_confident() => Some(self.freq),
+            _ => None,
+        }
+    }
+
+    // Quick bool check whether the record is past its deadline
+    pub fn is_overdue(&self) -> bool {
+        self.get_overdue().is_some()
+    }
+
+    // Re-evaluate the health state, returns the transition if the state changed
+    pub fn update_health(&mut self) -> Option<HealthTransition> {
+        if self.is_warming_up() || self.is_terminated() { return None };
+        let mut lateness = self.get_lateness();
+
+        // An overdue record is at least Missing, regardless of its average
+        if let Some(overdue) = self.get_overdue() {
+            let gap = match self.get_confident_freq() {
+                None => 0.0,
+                Some(freq) => overdue.since_last.as_secs_f32() / freq.as_secs_f32() - 1.0,
+            };
+            let floor = self.health.thresholds.missing.enter.max(gap);
+            lateness = Some(lateness.unwrap_or(0.0).max(floor));
+        }
+
+        // So is one that missed its most recent scheduled run, until the next
+        // run beats
+        if self.scheduled.is_some() {
+            let floor = match self.missed_last_run() {
+                true => self.health.thresholds.missing.enter,
+                false => 0.0,
+            };
+            lateness = Some(lateness.unwrap_or(0.0).max(floor));
+        }
+
+        // And one that is on time but mostly failing, see FAILING_RATIO
+        if self.get_failure_ratio().is_some_and(|r| r >= FAILING_RATIO) {
+            let floor = self.health.thresholds.missing.enter;
+            lateness = Some(lateness.unwrap_or(0.0).max(floor));
+        }
+        self.health.observe(lateness?, self.freq, self.clock.now())
+    }
+
+    // The task behind the record is gone. The record is kept in a terminal state,
+    // Completed, Panicked or Abandoned, until the Deck purges it.
+    pub fn terminate(&mut self, state: HealthState) -> Option<HealthTransition> {
+        if !state.is_terminal() { return None };
+        let transition = self.health.terminate(state, self.clock.now())?;
+        self.terminated = Some(self.clock.monotonic());
+        Some(transition)
+    }
+
+    pub fn is_terminated(&self) -> bool {
+        self.terminated.is_some()
+    }
+
+    // Whether the record has been terminal for longer than `retention`
+    pub fn is_expired(&self, retention: Duration) -> bool {
+        self.terminated.is_some_and(|at| self.clock.monotonic().checked_sub(at).is_some_and(|t| t >= retention))
+    }
+
     // Clear out any record of previous beats
     pub fn clear(&mut self) {
         self.raw_track.clear();
+        self.runs.clear();
+        self.open_span = None;
+        self.fields.clear();
+        self.outcomes.clear();
     }
 
     pub fn has_beat_since(&self, lrb: Option<&SystemTime>) -> bool {
-        self.raw_track.has_beat_since(*lrb.unwrap_or(&SystemTime::now()))
+        self.raw_track.has_beat_since(lrb.copied().unwrap_or_else(|| self.clock.now()))
     }
 
 
@@ -101,14 +832,15 @@ impl Record {
         // Add the last duration which is duration from last beat to now
         //TODO: Hacky patch just to get it to work, reimplement this bit immediately
         let mut extra = 0;
-        if let Ok(last_dur) = SystemTime::now().duration_since(*self.raw_track.back().unwrap()) {
+        if let Some(last_dur) = self.clock.monotonic().checked_sub(self.raw_track.0.back().unwrap().mono) {
             total_between_time += last_dur; 
             extra = 1;
         }
 
         // Calc and return the average delay duration between beats
-        let mut number_of_delays = self.raw_track.len() as u32 - 1;
+        let mut number_of_delays = self.raw_track.into_iter().linear().count() as u32;
         number_of_delays += extra;
+        if number_of_delays == 0 { return Some(Duration::from_secs(0)) };
         Some(total_between_time / number_of_delays)
     }
 
@@ -123,29 +855,47 @@ impl Record {
 
     // activity_rating provides a generalized health status
     pub fn get_activity_rating(&self) -> Result<ActivityRating> {
-        // Optimal    -> actual freq within 1% margin of expected freq
-        // NotOptimal -> actual freq outside more than 1% of expected freq
+        // Optimal    -> actual freq within the tolerance of expected freq
+        // NotOptimal -> actual freq outside the tolerance of expected freq
         // OnlyOnce   -> only one beat recorded, no actual frequency
         // NotOnce    -> Records of raw_track are empty
+        // Missing    -> Most recent beat is past its deadline, or the most
+        //               recent scheduled run never beat
+        // WarmingUp  -> Still within the warm-up window after deployment
+        // Failing    -> At least FAILING_RATIO of the recent outcomes failed
+        // Degraded   -> The most recent outcome was not a success
 
-        // Calculate optimal margin range
-        let exp_freq = self.freq.as_millis() as i128;
-        let margin = self.freq.mul_f32(0.02).as_millis() as i128;
-        let start = exp_freq - margin;
-        let end = exp_freq + margin;
+        if self.is_warming_up() { return Ok(ActivityRating::WarmingUp) };
+        if self.is_overdue() || self.missed_last_run() { return Ok(ActivityRating::Missing) };
+        if let Some(rating) = self.get_outcome_rating() { return Ok(rating) };
+        if !self.pattern.is_empty() { return self.get_pattern_rating() };
 
         // Determine if the real time freq average is optimal according
         // to the expected freq and return Activity Rating variant.
         if let Some(a) = self.get_average() {
-            match a.as_millis() as i128 {
-                0 => Ok(ActivityRating::OnlyOnce),
-                n if (start..=end).contains(&n) => Ok(ActivityRating::Optimal),
+            match a {
+                a if a.as_millis() == 0 => Ok(ActivityRating::OnlyOnce),
+                a if self.tolerance.contains(a, self.freq) => Ok(ActivityRating::Optimal),
                 _ => Ok(ActivityRating::NotOptimal),
             }
         } else {
             Ok(ActivityRating::NotOnce)
         }
     }
+    // As get_activity_rating, for records beating to a non-linear pattern. Every
+    // duration between beats must be within tolerance of what the pattern expects.
+    pub fn get_pattern_rating(&self) -> Result<ActivityRating> {
+        match self.raw_track.len() {
+            0 => return Ok(ActivityRating::NotOnce),
+            1 => return Ok(ActivityRating::OnlyOnce),
+            _ => {},
+        }
+        match self.get_pattern_beats().iter().all(|b| self.tolerance.contains(b.actual, b.expected)) {
+            true => Ok(ActivityRating::Optimal),
+            false => Ok(ActivityRating::NotOptimal),
+        }
+    }
+
     // Quick bool check whether the record is beating as expected
     pub fn is_optimal(&self) -> bool {
         if let Ok(ar) = self.get_activity_rating() {
@@ -160,7 +910,7 @@ impl Record {
     pub fn get_beats(&self, lbr: Option<&SystemTime>) -> Option<Vec<SystemTime>> {
         match self.raw_track.hack_track(lbr, None) {
             Some(raw_track) if raw_track.len() > 0 => {
-                Some(raw_track.0.iter().map(|x| x.clone()).collect::<Vec<SystemTime>>())
+                Some(raw_track.0.iter().map(|x| x.wall).collect::<Vec<SystemTime>>())
             }
             _ => None,
         }
@@ -178,23 +928,64 @@ impl Record {
         }
     }
 
-    // A very very basic implementation of a self determination of beat frequency. This
-    // requires a lot more logic to achieve accuracy. Maybe a confidence rating as well?
-    // TODO: This needs to be much smarter
-    // pub fn guess_freq(&self) -> Option<Duration> {
-    //     match self.get_beats(None) {
-    //         // If no beats
-    //         None => None,
-    //         // If one beat, assume duration between the beats deployed time and actual transmit time
-    //         Some(beats) if beats.len() == 1 => self.deployment.duration_since(*beats[0]).ok(),
-    //         // If two beats, assume duration to be similiar to duration between the two timestamps
-    //         Some(beats) if beats.len() == 2 => beats[1].duration_since(*beats[0]).ok(),
-    //         // If more than two, get an average of duration between timestamps
-    //         Some(_) => self.get_average(),
-    //         // Anything else
-    //         _ => None,
-    //     }
-    // }
+    // Summary stats of the durations between the beats currently in the track
+    pub fn get_interval_stats(&self) -> Option<IntervalStats> {
+        let intervals: Vec<Duration> = self.raw_track.into_iter().linear().collect();
+        IntervalStats::from_durations(&intervals, EWMA_ALPHA)
+    }
+
+    // Summary stats of how long the spans of work took. Compared with the interval
+    // stats, a slow loop has long runs while a loop sleeping too long has short
+    // runs and long intervals.
+    pub fn get_run_stats(&self) -> Option<IntervalStats> {
+        let runs = self.runs.iter().copied().collect::<Vec<Duration>>();
+        IntervalStats::from_durations(&runs, EWMA_ALPHA)
+    }
+
+    // Self determination of beat frequency with a confidence level. The median
+    // interval is used as the guess, the confidence depends on how many intervals
+    // it is based on and how much they vary.
+    pub fn guess_freq(&self) -> Option<(Duration, ConfidenceLevel)> {
+        match self.get_interval_stats() {
+            // If one beat, assume duration between the deployed time and the beat
+            None => self.raw_track.back()
+                .and_then(|b| b.duration_since(self.deployment).ok())
+                .map(|d| (d, ConfidenceLevel::Nil)),
+            Some(stats) => {
+                let variation = match stats.mean.as_secs_f64() {
+                    m if m > 0.0 => stats.stddev.as_secs_f64() / m,
+                    _ => f64::MAX,
+                };
+                let confidence = match (stats.count, variation) {
+                    (c, v) if c >= 10 && v <= 0.05 => ConfidenceLevel::Very,
+                    (c, v) if c >= 5 && v <= 0.15 => ConfidenceLevel::Likely,
+                    (_, v) if v <= 0.5 => ConfidenceLevel::Maybe,
+                    _ => ConfidenceLevel::Nil,
+                };
+                Some((stats.p50, confidence))
+            },
+        }
+    }
+
+    // Update the inferred freq from the beats so far. It becomes the expected freq
+    // unless the user has set one, or there is no confidence in it.
+    pub fn infer_freq(&mut self) {
+        self.inferred_freq = self.guess_freq();
+        if self.user_freq.is_some() { return };
+        match self.inferred_freq {
+            Some((freq, ref c)) if *c != ConfidenceLevel::Nil => self.freq = freq,
+            _ => {},
+        }
+    }
+
+    // The confidence in the expected freq currently in use
+    pub fn get_freq_confidence(&self) -> ConfidenceLevel {
+        match (&self.user_freq, &self.inferred_freq) {
+            (Some(_), _) => ConfidenceLevel::UserDefined,
+            (None, Some((_, c))) => c.clone(),
+            _ => ConfidenceLevel::Nil,
+        }
+    }
 
 }
 
@@ -0,0 +1,129 @@
+use std::time::Duration;
+use std::sync::atomic::{AtomicU64, AtomicBool, Ordering, fence};
+use std::thread;
+
+// ////////////////////////////////////////////////////////////////////////
+// Beat Ring
+// /////////////////////////////////////////////////////////////
+
+// A fixed size, lock free ring of beats, shared between a Beat and the Deck.
+// Beat::now only reserves a slot and writes the monotonic time of the beat into
+// it, so tight loops are not held up by the channel or any allocation. The Deck
+// drains the ring every DRAIN_TICK, turning the beats back into stamps.
+//
+// Any number of threads may write, but only the Deck reads. When the writers
+// get a full lap ahead of the Deck the oldest beats are overwritten, the Deck
+// counts those as lost. So is a beat whose slot a writer on a later lap has
+// already taken.
+//
+// Once the Deck shuts down it closes the ring, telling the Beat that nobody is
+// going to read what it writes.
+pub struct BeatRing {
+    head: AtomicU64,    // Position of the next write
+    tail: AtomicU64,    // Position of the next read, only moved by the Deck
+    closed: AtomicBool,
+    slots: Box<[Slot]>,
+}
+
+// Each slot is a tiny seqlock. The seq is the position plus one once the beat at
+// that position can be read, with the WRITING bit set while it is being written.
+// A writer takes the slot over from an earlier lap only, so two writers never
+// write the same slot at once.
+struct Slot {
+    seq: AtomicU64,
+    mono: AtomicU64,    // Nanos on the clock's monotonic timeline
+}
+
+const WRITING: u64 = 1 << 63;
+
+impl BeatRing {
+
+    pub fn new(capacity: usize) -> Self {
+        let slots = (0..capacity.max(1))
+            .map(|_| Slot { seq: AtomicU64::new(0), mono: AtomicU64::new(0) })
+            .collect::<Vec<Slot>>();
+        BeatRing {
+            head: AtomicU64::new(0),
+            tail: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+            slots: slots.into_boxed_slice(),
+        }
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.slots.len() as u64
+    }
+
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Release);
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    // Record a beat at `mono` on the monotonic timeline
+    pub fn push(&self, mono: Duration) {
+        let pos = self.head.fetch_add(1, Ordering::Relaxed);
+        let slot = &self.slots[(pos % self.capacity()) as usize];
+        let mut seq = slot.seq.load(Ordering::Acquire);
+        loop {
+            // Taken by a writer on a later lap, this beat is lost
+            if seq & !WRITING > pos { return };
+            // A writer on an earlier lap is still at it, let it finish
+            if seq & WRITING != 0 {
+                thread::yield_now();
+                seq = slot.seq.load(Ordering::Acquire);
+                continue
+            }
+            match slot.seq.compare_exchange_weak(seq, (pos + 1) | WRITING, Ordering::Acquire, Ordering::Relaxed) {
+                Ok(_) => break,
+                Err(current) => seq = current,
+            }
+        }
+        fence(Ordering::Release);
+        slot.mono.store(mono.as_nanos() as u64, Ordering::Relaxed);
+        slot.seq.store(pos + 1, Ordering::Release);
+    }
+
+    // Hand every beat written since the last drain to `f`, oldest first. A beat
+    // whose slot is still being written ends the drain, it is picked up next
+    // time. Returns how many beats were overwritten before they could be read.
+    pub fn drain<F: FnMut(Duration)>(&self, mut f: F) -> u64 {
+        let head = self.head.load(Ordering::Acquire);
+        let mut tail = self.tail.load(Ordering::Relaxed);
+        let mut lost = 0;
+        if head - tail > self.capacity() {
+            lost = head - tail - self.capacity();
+            tail = head - self.capacity();
+        }
+        while tail < head {
+            let slot = &self.slots[(tail % self.capacity()) as usize];
+            let seq = slot.seq.load(Ordering::Acquire);
+            if seq == tail + 1 {
+                let mono = slot.mono.load(Ordering::Relaxed);
+                fence(Ordering::Acquire);
+                if slot.seq.load(Ordering::Relaxed) == seq {
+                    f(Duration::from_nanos(mono));
+                    tail += 1;
+                    continue
+                }
+            }
+            // Overwritten by a writer on a later lap
+            if seq & !WRITING > tail + 1 {
+                lost += 1;
+                tail += 1;
+                continue
+            }
+            break
+        }
+        self.tail.store(tail, Ordering::Relaxed);
+        lost
+    }
+}
+
+impl std::fmt::Debug for BeatRing {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "BeatRing {{ head: {:?}, tail: {:?} }}", self.head, self.tail)
+    }
+}
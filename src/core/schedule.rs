@@ -0,0 +1,156 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::str::FromStr;
+
+use crate::{TE, Result};
+
+// ////////////////////////////////////////////////////////////////////////
+// Schedule
+// /////////////////////////////////////////////////////////////
+
+// A cron style schedule for tasks that run on a calendar rather than at a fixed
+// freq. Uses the common five fields, evaluated in UTC:
+//
+//      minute (0-59) hour (0-23) day-of-month (1-31) month (1-12) day-of-week (0-7)
+//
+// Each field takes `*`, a value, a range `a-b`, a step `*/n` or `a-b/n`, or a
+// comma separated list of those. Sunday is either 0 or 7. As with cron, when
+// both day fields are restricted a day matching either of them is a match.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Schedule {
+    expr: String,
+    minutes: u64,       // Bit per allowed value
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,      // Day-of-month field is `*`
+    any_weekday: bool,  // Day-of-week field is `*`
+}
+
+const SECS_PER_DAY: u64 = 86_400;
+
+// Don't look further ahead than this for the next run, a schedule such as
+// February 30th would otherwise search forever
+const MAX_DAYS_AHEAD: u64 = 366 * 5;
+
+impl Schedule {
+
+    pub fn parse(expr: &str) -> Result<Schedule> {
+        let fields = expr.split_whitespace().collect::<Vec<&str>>();
+        if fields.len() != 5 {
+            return Err(TE::InvalidSchedule(format!("expected 5 fields in '{}'", expr)))
+        }
+
+        // Sunday can be either 0 or 7
+        let mut weekdays = parse_field(fields[4], 0, 7)?;
+        if weekdays & 1 << 7 != 0 { weekdays |= 1 };
+
+        Ok(Schedule {
+            expr: expr.to_string(),
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.expr
+    }
+
+    // The first scheduled run strictly after `after`
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let secs = after.duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+        // Runs are on whole minutes, start at the next one
+        let start = (secs / 60 + 1) * 60;
+        let first_day = start / SECS_PER_DAY;
+
+        for day in first_day..first_day + MAX_DAYS_AHEAD {
+            if !self.matches_day(day) { continue };
+            for hour in 0..24 {
+                if self.hours & 1 << hour == 0 { continue };
+                for minute in 0..60 {
+                    if self.minutes & 1 << minute == 0 { continue };
+                    let run = day * SECS_PER_DAY + hour * 3600 + minute * 60;
+                    if run >= start {
+                        return UNIX_EPOCH.checked_add(Duration::from_secs(run))
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_day(&self, day: u64) -> bool {
+        let (_, month, dom) = civil_from_days(day);
+        if self.months & 1 << month == 0 { return false };
+
+        // 1970-01-01 was a Thursday
+        let weekday = (day + 4) % 7;
+        let dom_match = self.days & 1 << dom != 0;
+        let dow_match = self.weekdays & 1 << weekday != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => dow_match,
+            (false, true) => dom_match,
+            (false, false) => dom_match || dow_match,
+        }
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = TE;
+    fn from_str(expr: &str) -> Result<Self> {
+        Schedule::parse(expr)
+    }
+}
+
+// Parse a single cron field into a bit set of the allowed values
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let invalid = || TE::InvalidSchedule(format!("invalid field '{}'", field));
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((r, s)) => (r, s.parse::<u64>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            r => match r.split_once('-') {
+                Some((a, b)) => (
+                    a.parse::<u64>().map_err(|_| invalid())?,
+                    b.parse::<u64>().map_err(|_| invalid())?,
+                ),
+                None => {
+                    let v = r.parse::<u64>().map_err(|_| invalid())?;
+                    // A single value with a step runs from the value to the max
+                    if step > 1 { (v, max) } else { (v, v) }
+                },
+            },
+        };
+        if step == 0 || start < min || end > max || start > end { return Err(invalid()) };
+        for v in (start..=end).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+// Year, month and day of month from days since the unix epoch
+// http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
@@ -0,0 +1,66 @@
+use std::time::Duration;
+
+// ////////////////////////////////////////////////////////////////////////
+// Interval Stats
+// /////////////////////////////////////////////////////////////
+
+// A summary of a series of durations, such as the intervals between beats
+// from the LinearExt iterator. Calculated on request, nothing here is kept
+// up to date by the Deck.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntervalStats {
+    pub count: usize,       // Number of intervals summarized
+    pub min: Duration,
+    pub max: Duration,
+    pub mean: Duration,
+    pub stddev: Duration,   // Population standard deviation
+    pub jitter: Duration,   // Mean difference between consecutive intervals
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub ewma: Duration,     // Exponentially weighted, recent intervals weigh more
+}
+
+impl IntervalStats {
+
+    // Returns None if there are no durations to summarize. The alpha (0.0 - 1.0)
+    // is how much weight each new interval has on the ewma.
+    pub fn from_durations(durations: &[Duration], alpha: f64) -> Option<Self> {
+        if durations.is_empty() { return None };
+
+        let secs = durations.iter().map(|d| d.as_secs_f64()).collect::<Vec<f64>>();
+        let count = secs.len();
+        let mean = secs.iter().sum::<f64>() / count as f64;
+        let variance = secs.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / count as f64;
+
+        let jitter = match count {
+            1 => 0.0,
+            _ => secs.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (count - 1) as f64,
+        };
+
+        let ewma = secs[1..].iter().fold(secs[0], |e, s| alpha * s + (1.0 - alpha) * e);
+
+        let mut sorted = durations.to_vec();
+        sorted.sort();
+
+        Some(IntervalStats {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean: Duration::from_secs_f64(mean),
+            stddev: Duration::from_secs_f64(variance.sqrt()),
+            jitter: Duration::from_secs_f64(jitter),
+            p50: percentile(&sorted, 50.0),
+            p90: percentile(&sorted, 90.0),
+            p99: percentile(&sorted, 99.0),
+            ewma: Duration::from_secs_f64(ewma),
+        })
+    }
+}
+
+// Nearest rank percentile of already sorted durations
+pub fn percentile(sorted: &[Duration], percent: f64) -> Duration {
+    if sorted.is_empty() { return Duration::from_secs(0) };
+    let rank = (percent / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
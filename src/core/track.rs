@@ -7,8 +7,7 @@ use std::cmp::{Ordering};
 
 use std::iter::{ExactSizeIterator, Iterator};
 
-// use crate::{Result, TE};
-use crate::{TE, BEAT_CAP};
+use crate::{Result, TE, BEAT_CAP};
 
 // ////////////////////////////////////////////////////////////////////////
 // Track 
@@ -22,11 +21,20 @@ use crate::{TE, BEAT_CAP};
 // 
 // /////////////////////////////////////////////////////////////
 
+// A single beat. Durations between beats are measured on the monotonic
+// timeline, so stepping the wall clock doesn't distort them. The wall clock
+// timestamp is kept for exporting the beat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    pub mono: Duration,     // Position on the clock's monotonic timeline
+    pub wall: SystemTime,   // Wall clock time of the beat
+}
+
 #[derive(Clone, Debug)]
-pub struct Track(pub VecDeque<SystemTime>);
+pub struct Track(pub VecDeque<Stamp>);
 
 impl<'a> IntoIterator for &'a Track {
-    type Item = &'a SystemTime;
+    type Item = &'a Stamp;
     type IntoIter =  TrackIter<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
@@ -44,7 +52,7 @@ pub struct TrackIter<'a> {
 }
 
 impl<'a> Iterator for TrackIter<'a> {
-    type Item = &'a SystemTime;
+    type Item = &'a Stamp;
     fn next (&mut self) -> Option<Self::Item> {
         let res = match self.track.0.get(self.idx) {
             Some(timestamp) =>Some(timestamp) ,
@@ -57,14 +65,27 @@ impl<'a> Iterator for TrackIter<'a> {
 
 impl Track {
 
-    pub fn add(&mut self, timestamp: SystemTime) {
-        // TODO: Proper validation on what's being pushed into vecdeque
-        self.0.push_back(timestamp);
-        while self.0.len() > BEAT_CAP {
+    // Beats are kept in order on the monotonic timeline, a beat that arrives late
+    // is slotted in where it belongs. Most beats arrive in order, so the search
+    // starts from the back. A beat at the same moment as one already on the
+    // track is rejected. Only the most recent <BEAT_CAP> beats are kept.
+    pub fn add(&mut self, stamp: Stamp) -> Result<()> {
+        self.add_capped(stamp, BEAT_CAP)
+    }
+
+    // As add, keeping the most recent <cap> beats
+    pub fn add_capped(&mut self, stamp: Stamp, cap: usize) -> Result<()> {
+        let idx = match self.0.iter().rposition(|b| b.mono <= stamp.mono) {
+            Some(i) if self.0[i].mono == stamp.mono => return Err(TE::DuplicateBeat),
+            Some(i) => i + 1,
+            None => 0,
+        };
+        self.0.insert(idx, stamp);
+        while self.0.len() > cap {
             self.0.pop_front();
         }
-
-    } 
+        Ok(())
+    }
 
     pub fn clear(&mut self) {
         self.0.clear()
@@ -78,21 +99,23 @@ impl Track {
         self.0.is_empty()
     }
 
+    // Wall clock time of the most recent beat
     pub fn back(&self) -> Option<&SystemTime> {
-        self.0.back()
+        self.0.back().map(|s| &s.wall)
     }
 
+    // Wall clock time of the oldest beat
     pub fn front(&self) -> Option<&SystemTime> {
-        self.0.front()
+        self.0.front().map(|s| &s.wall)
     }
 
     pub fn hack_track(&self, from: Option<&SystemTime>, to: Option<&SystemTime>) -> Option<Self> {
         let bv = self.0.iter()
             // .filter(|b| b > from_ && b < to_)
-            .filter(|b| from.is_none() || b > &from.unwrap_or(&UNIX_EPOCH))       // Performance?
-            .filter(|b| to.is_none() || b < &to.unwrap_or(&SystemTime::now()))    //
+            .filter(|b| from.is_none() || &b.wall > from.unwrap_or(&UNIX_EPOCH))  // Performance?
+            .filter(|b| to.is_none_or(|to| b.wall < *to))                          //
             .map(|b| b.to_owned())
-            .collect::<VecDeque<SystemTime>>();
+            .collect::<VecDeque<Stamp>>();
         if bv.len() > 0 {
             return Some(Track(bv))
         } else {
@@ -101,12 +124,17 @@ impl Track {
     }
     
     pub fn has_beat_since(&self, since: SystemTime) -> bool {
-        match self.0.back() {
+        match self.back() {
             Some(b) if b > &since => true,
             _ => false,
         }
     }
 
+    // Time elapsed between the most recent beat and `now`, on the monotonic timeline
+    pub fn since_last(&self, now: Duration) -> Option<Duration> {
+        self.0.back().map(|b| now.checked_sub(b.mono).unwrap_or_default())
+    }
+
 }
 // ////////////////////////////////////////////////////////////////////////
 // Helping Iters
@@ -119,7 +147,7 @@ pub struct LinearBeat(Duration);
 
 pub struct LinearBeatsIter<I: Iterator> { 
     iter: Enumerate<I>,
-    lbr: SystemTime ,
+    lbr: Duration,
 }
 
 pub trait LinearExt<'a>: Iterator {
@@ -129,7 +157,7 @@ pub trait LinearExt<'a>: Iterator {
     {
         LinearBeatsIter {
             iter: self.enumerate(),
-            lbr: SystemTime::now(),
+            lbr: Duration::from_secs(0),
         }
     }
 }
@@ -147,44 +175,104 @@ impl Deref for LinearBeat {
 impl<'a> LinearExt<'a> for TrackIter<'_> {}
 
 // This iterator is built on top of the tracks iterator, it takes two
-// timestamps and creates a duration (aka beat) between the two.
+// timestamps and creates a duration (aka beat) between the two. A beat that is
+// out of order is skipped, rather than ending the iteration.
 impl<'a, I>Iterator for LinearBeatsIter<I>
 where 
-    I: Iterator<Item = &'a SystemTime>  + Copy
+    I: Iterator<Item = &'a Stamp>  + Copy
 {
     type Item = LinearBeat;
     
     fn next(&mut self) -> Option<Self::Item> {
-        match self.iter.next() {
-            Some(mut next) => {
-                
-                // Skip the first timestamp, as one is not enough
-                // to generate a duration
-                if next.0 == 0 {
-                    self.lbr = *next.1;
-                    match self.iter.next() {
-                        Some(n) => next = n,
-                        _ => return None,
-                    }
-                
-                }                
-
-                // Compare the currently focused timestamp with the
-                // last recorded timestamp a.k.a lbr
-                match next.1.duration_since(self.lbr) {
-                    Ok(d) => {
-                        self.lbr = *next.1;
-                        Some(LinearBeat(d))
-                    },
-                     _ => None,
-                }
-            },
-            _ => None
- 
+        loop {
+            let (idx, stamp) = self.iter.next()?;
+
+            // Skip the first timestamp, as one is not enough
+            // to generate a duration
+            if idx == 0 {
+                self.lbr = stamp.mono;
+                continue
+            }
+
+            // Compare the currently focused timestamp with the
+            // last recorded timestamp a.k.a lbr
+            if let Some(d) = stamp.mono.checked_sub(self.lbr) {
+                self.lbr = stamp.mono;
+                return Some(LinearBeat(d))
+            }
         }
     }
 }
 
+// Pattern beat iterator
+// Pairs each duration between beats with the duration the pattern expected it
+// to be. The pattern repeats, and is started at whichever phase best fits the
+// track as the track does not necessarily begin at the start of a cycle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PatternBeat {
+    pub actual: Duration,
+    pub expected: Duration,
+}
+
+pub struct PatternBeatsIter<I: Iterator> {
+    iter: LinearBeatsIter<I>,
+    pattern: Vec<Duration>,
+    idx: usize,
+}
+
+impl<I: Iterator> PatternBeatsIter<I> {
+    // The duration the pattern expects next, once the beats are exhausted this is
+    // the expected duration until the upcoming beat
+    pub fn expected_next(&self) -> Option<Duration> {
+        match self.pattern.len() {
+            0 => None,
+            n => Some(self.pattern[self.idx % n]),
+        }
+    }
+}
+
+pub trait PatternExt<'a>: Iterator {
+    fn pattern(self, pattern: &[Duration]) -> PatternBeatsIter<Self>
+    where 
+        Self: Sized + LinearExt<'a> + Iterator<Item = &'a Stamp> + Copy
+    {
+        let intervals: Vec<Duration> = self.linear().collect();
+        PatternBeatsIter {
+            iter: self.linear(),
+            pattern: pattern.to_vec(),
+            idx: best_phase(&intervals, pattern),
+        }
+    }
+}
+
+impl<'a> PatternExt<'a> for TrackIter<'_> {}
+
+impl<'a, I>Iterator for PatternBeatsIter<I>
+where 
+    I: Iterator<Item = &'a Stamp>  + Copy
+{
+    type Item = PatternBeat;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let expected = self.expected_next()?;
+        let actual = *self.iter.next()?;
+        self.idx += 1;
+        Some(PatternBeat { actual, expected })
+    }
+}
+
+// The offset into the pattern with the least total difference to the intervals
+fn best_phase(intervals: &[Duration], pattern: &[Duration]) -> usize {
+    let diff = |a: &Duration, b: &Duration| if a > b { *a - *b } else { *b - *a };
+    (0..pattern.len())
+        .min_by_key(|phase| {
+            intervals.iter().enumerate()
+                .map(|(i, d)| diff(d, &pattern[(phase + i) % pattern.len()]))
+                .sum::<Duration>()
+        })
+        .unwrap_or(0)
+}
+
 // Linear beat iterator into a Vec of Durations
 impl<'a> FromIterator<LinearBeat> for Vec<Duration> {
     fn from_iter<I: IntoIterator<Item=LinearBeat>>(iter: I) -> Self {
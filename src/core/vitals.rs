@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ////////////////////////////////////////////////////////////////////////
+// Vitals
+// /////////////////////////////////////////////////////////////
+
+// Thumper watching itself. The Deck and the Output note every time they go
+// round their loop, and count the restarts and faults they recover from. A panic
+// in either is caught and the runtime restarted, the Deck from the record map
+// it last published. TheDJ reads these back as a RuntimeHealth.
+#[derive(Debug)]
+pub struct Vitals {
+    origin: Instant,
+    deck_seen: AtomicU64,               // Nanos after origin the Deck last went round
+    output_seen: AtomicU64,             // Nanos after origin the Output last went round
+    pub(crate) deck_restarts: AtomicU64,
+    pub(crate) output_restarts: AtomicU64,
+    pub(crate) report_errors: AtomicU64,        // Errors returned by a report
+    pub(crate) report_init_failures: AtomicU64, // Reports that failed to init, and were dropped
+    pub(crate) dropped_replies: AtomicU64,      // Replies nobody was waiting for any more
+}
+
+impl Default for Vitals {
+    fn default() -> Self {
+        Vitals {
+            origin: Instant::now(),
+            deck_seen: AtomicU64::new(0),
+            output_seen: AtomicU64::new(0),
+            deck_restarts: AtomicU64::new(0),
+            output_restarts: AtomicU64::new(0),
+            report_errors: AtomicU64::new(0),
+            report_init_failures: AtomicU64::new(0),
+            dropped_replies: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Vitals {
+
+    pub fn new() -> Self { Vitals::default() }
+
+    pub(crate) fn count(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn deck_alive(&self) {
+        self.deck_seen.store(self.origin.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    pub(crate) fn output_alive(&self) {
+        self.output_seen.store(self.origin.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    // How long since the Deck last went round its loop
+    pub fn deck_silence(&self) -> Duration {
+        Self::silence(self.origin, &self.deck_seen)
+    }
+
+    // How long since the Output last went round its loop
+    pub fn output_silence(&self) -> Duration {
+        Self::silence(self.origin, &self.output_seen)
+    }
+
+    fn silence(origin: Instant, seen: &AtomicU64) -> Duration {
+        origin.elapsed().saturating_sub(Duration::from_nanos(seen.load(Ordering::Relaxed)))
+    }
+}
+
+// ////////////////////////////////////////////////////////////////////////
+// Runtime Health
+// /////////////////////////////////////////////////////////////
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RuntimeState {
+    Running,
+    Stalled(Duration),  // Hasn't gone round its loop for longer than Config::deck_stall or output_stall
+    Dead,               // The thread has ended without being shut down
+    Stopped,            // Shut down
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeHealth {
+    pub deck: RuntimeState,
+    pub output: Option<RuntimeState>,   // None when the DJ isn't reporting
+    pub deck_restarts: u64,
+    pub output_restarts: u64,
+    pub report_errors: u64,
+    pub report_init_failures: u64,
+    pub dropped_replies: u64,
+}
+
+impl RuntimeHealth {
+    pub(crate) fn new(deck: RuntimeState, output: Option<RuntimeState>, vitals: &Vitals) -> Self {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        RuntimeHealth {
+            deck,
+            output,
+            deck_restarts: load(&vitals.deck_restarts),
+            output_restarts: load(&vitals.output_restarts),
+            report_errors: load(&vitals.report_errors),
+            report_init_failures: load(&vitals.report_init_failures),
+            dropped_replies: load(&vitals.dropped_replies),
+        }
+    }
+
+    // Every runtime running, nothing restarted
+    pub fn is_healthy(&self) -> bool {
+        self.deck == RuntimeState::Running
+            && self.output.is_none_or(|o| o == RuntimeState::Running)
+            && self.deck_restarts == 0
+            && self.output_restarts == 0
+    }
+}
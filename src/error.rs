@@ -39,9 +39,39 @@ pub enum ThumperError {
     #[error("The record requested does not exist")]
     MissingRecord,
 
+    #[error("The record requested has been replaced by another with the same id")]
+    StaleHandle,
+
     #[error("Record registration failure: {0}")]
 	RegisterFail(&'static str),
 
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
+
+    #[error("A beat with the same timestamp already exists")]
+    DuplicateBeat,
+
+    #[error("The beat's timestamp is in the future")]
+    FutureBeat,
+
+    #[error("The beat's timestamp is older than the record accepts")]
+    StaleBeat,
+
+    #[error("The field name is reserved: {0}")]
+    ReservedField(String),
+
+    #[error("Invalid setting: {0}")]
+    InvalidSetting(&'static str),
+
+    #[error("Invalid configuration: {0}")]
+    InvalidConfig(&'static str),
+
+    #[error("The runtime has been shut down")]
+    ShutDown,
+
+    #[error("A runtime thread panicked: {0}")]
+    ThreadPanicked(&'static str),
+
     #[error("There are no new records to report")]
 	NothingNewToReport,
 
@@ -5,13 +5,20 @@ pub mod core;
 
 pub use crate::output::{Output, Report, DM2OutputRunner, InfluxDB};
 pub use crate::core::{TheDJ, DM2DJ};
-pub use crate::core::{Track, LinearExt, LinearBeat};
-pub use crate::core::{Beat};
-pub use crate::core::{Record, ActivityRating};
-pub use crate::core::{Deck, DM2Deck, Arm};
+pub use crate::core::{Track, Stamp, LinearExt, LinearBeat, PatternExt, PatternBeat};
+pub use crate::core::{Beat, Span};
+pub use crate::core::{Record, ActivityRating, Outcome, Deadline, Overdue, Margin, Tolerance, BetterTo, ConfidenceLevel};
+pub use crate::core::{IntervalStats, Schedule, Scheduled};
+pub use crate::core::{Health, HealthState, HealthThresholds, HealthTransition, Threshold};
+pub use crate::core::{Deck, DM2Deck, Arm, Handle, Archive, Archived, BeatRing, Notifier};
+pub use crate::core::{Vitals, RuntimeHealth, RuntimeState, Config, TheDJBuilder};
+pub use crate::core::{Clock, SharedClock, SystemClock, MockClock};
 
 pub use crate::error::{TE, Result};
 
+use std::time::Duration;
+use std::collections::HashMap;
+
 #[cfg(test)]
 mod test;
 
@@ -20,37 +27,64 @@ mod test;
 // /////////////////////////////////////////////////////////////
 pub const RECORD_CAP: usize = 1000;
 pub const BEAT_CAP: usize = 100;
+pub const DECK_TICK: Duration = Duration::from_secs(1);
+pub const EWMA_ALPHA: f64 = 0.2;
+pub const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(1);
+pub const OUTCOME_WINDOW: usize = 20;
+pub const FAILING_RATIO: f32 = 0.5;
+pub const TERMINAL_RETENTION: Duration = Duration::from_secs(300);
+pub const ARCHIVE_CAP: usize = 100;
+pub const RING_CAP: usize = 1024;
+pub const DRAIN_TICK: Duration = Duration::from_millis(10);
+pub const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+pub const STALL_THRESHOLD: Duration = Duration::from_secs(5);
+pub const STALL_TICKS: u32 = 2;
+pub const OUTPUT_TICK: Duration = Duration::from_secs(1);
+pub const CHANNEL_BOUND: usize = 10_000;
+pub const RESERVED_FIELDS: &[&str] = &["expected"];
 
 // ////////////////////////////////////////////////////////////////////////
 // ID Indexer 
 // /////////////////////////////////////////////////////////////
 
-// Used to keep a running index of heart beats we monitor
+// Used to keep a running index of heart beats we monitor. Ids are reused, each
+// time one is handed out again its generation goes up. Only <cap> ids are handed
+// out at once.
 struct Indexer {
+    cap: usize,
     next_index: i32,
     in_use: Vec<i32>,
+    generations: HashMap<i32, u32>,
 }
 
 impl Indexer {
 
     fn new() -> Indexer{
-        Indexer {next_index: 0, in_use: Vec::new()}
+        Self::with_cap(RECORD_CAP)
+    }
+
+    fn with_cap(cap: usize) -> Indexer{
+        Indexer {cap, next_index: 0, in_use: Vec::new(), generations: HashMap::new()}
     }
 
-    fn next(&mut self) -> Result<i32>  {
-        if let Some(n) = (0..RECORD_CAP as i32)
-            .into_iter()
-            .filter(|x| !self.in_use.contains(x))
-            .next() 
-        {
+    fn next(&mut self) -> Result<Handle>  {
+        if let Some(n) = (0..self.cap as i32).find(|x| !self.in_use.contains(x)) {
             self.in_use.push(n);
-            Ok(n)
+            let generation = self.generations.entry(n)
+                .and_modify(|g| *g = g.wrapping_add(1))
+                .or_insert(0);
+            Ok(Handle { id: n, generation: *generation })
         } else { Err(TE::MaximumCapacity) }
     }
 
     fn remove(&mut self, job_id: i32) {
         self.in_use.retain(|&x| x != job_id);
     }
+
+    // Keep only the ids still in use, generations are kept either way
+    fn retain<F: FnMut(i32) -> bool>(&mut self, mut f: F) {
+        self.in_use.retain(|&x| f(x));
+    }
 }
 
 
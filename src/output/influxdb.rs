@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use reqwest::header::{HeaderValue, AUTHORIZATION};
 
-use crate::{TE, Record, output::Report};
+use crate::{TE, Record, output::Report, RESERVED_FIELDS};
 
 // InfluxDB //////////////////////////////////////////
 // After trying:
@@ -50,9 +50,7 @@ impl Report for InfluxDB {
             let mut latest_beat = UNIX_EPOCH;
             // Syntax <measurement>[,<tag_key>=<tag_value>[,<tag_key>=<tag_value>]] <field_key>=<field_value>[,<field_key>=<field_value>] [<timestamp>]
             for beat in beats {
-                // TODO: Do something else if fails
-                let ts = beat.duration_since(UNIX_EPOCH).expect("Marty!").as_nanos() as i64;
-                let msg = format!("{},beatname={} expected={} {}", self.name, record.name, record.freq.as_secs(), ts);
+                let msg = line(&self.name, record, beat);
                 let client = reqwest::blocking::Client::new();
                 client.post(&addy)
                     .body(msg)
@@ -68,3 +66,23 @@ impl Report for InfluxDB {
     } 
     fn end(&self) -> Result<(), TE> {Ok(())}
 }
+
+// A beat in line protocol, along with any fields that were sent with it
+pub(crate) fn line(measurement: &str, record: &Record, beat: SystemTime) -> String {
+    // TODO: Do something else if fails
+    let ts = beat.duration_since(UNIX_EPOCH).expect("Marty!").as_nanos() as i64;
+    let mut fields = format!("expected={}", record.freq.as_secs());
+    // InfluxDB has no NaN or infinity, those are left out, as is anything that
+    // would clash with the fields written above
+    let fields_at = record.get_fields_at(beat).into_iter()
+        .filter(|(name, v)| v.is_finite() && !RESERVED_FIELDS.contains(name));
+    for (name, value) in fields_at {
+        fields.push_str(&format!(",{}={}", escape_key(name), value));
+    }
+    format!("{},beatname={} {} {}", measurement, record.name, fields, ts)
+}
+
+// Field keys escape commas, equals signs and spaces
+fn escape_key(key: &str) -> String {
+    key.replace(',', "\\,").replace('=', "\\=").replace(' ', "\\ ")
+}
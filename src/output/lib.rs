@@ -1,13 +1,14 @@
-use std::time::{SystemTime, Duration, UNIX_EPOCH};
+use std::time::{SystemTime, Duration};
 use std::sync::{Arc, mpsc, RwLock};
 use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
 
-use crate::{DM2Deck, TE, Record};
+use crate::{DM2Deck, TE, Record, Archive, Archived, SharedClock, Vitals};
 
 // ////////////////////////////////////////////////////////////////
 // Type less
 // ///////////////////////////////////////////////////
-type AtomicRecordMap = Arc<RwLock<HashMap<i32, Record>>>;
+type AtomicRecordMap = Arc<RwLock<HashMap<i32, Arc<Record>>>>;
 type ReportsAndTheirLBRs = Vec<(Box<dyn Report>, SystemTime)>;
 
 // ////////////////////////////////////////////////////////////////
@@ -16,7 +17,8 @@ type ReportsAndTheirLBRs = Vec<(Box<dyn Report>, SystemTime)>;
 #[derive(Debug)]
 pub enum DM2OutputRunner {
     RegisterOutput(Box<dyn Report>),
-    StopOutput,
+    TickOutput(mpsc::Sender<Result<(), TE>>),  // Run the reports that are due now, replied to once done
+    StopOutput(mpsc::Sender<Result<(), TE>>),  // Replied to once the reports have ended
 }
 
 // ////////////////////////////////////////////////////////////////
@@ -26,11 +28,16 @@ pub enum DM2OutputRunner {
 // data collected in the program's main loop, and then processes and delivers
 // it to it's destination. The destination is described when building the
 // report object. This object will be called from the Output runtime to execute.
+// Besides the raw beats, reports can pull analytics such as
+// `record.get_interval_stats()` from the record they are run with.
+// Records that have been archived are handed to `run_archived` once, which
+// does nothing unless the report implements it.
 pub trait Report: Send {
     fn duration(&self)                 -> Result<Duration, TE>;
     fn init(&self)                     -> Result<(), TE>;
     fn run(&mut self, record: &Record) -> Result<(), TE>;
     fn end(&self)                      -> Result<(), TE>;
+    fn run_archived(&mut self, _archived: &Archived) -> Result<(), TE> { Ok(()) }
 }
 
 impl std::fmt::Debug for dyn Report {
@@ -45,7 +52,8 @@ impl std::fmt::Debug for dyn Report {
 pub struct ReportWrapper {
     report: Box<dyn Report>,
     freq: Duration,
-    last: SystemTime,
+    last: Duration,     // On the clock's monotonic timeline
+    archived: u64,      // Seq of the last archived record handed to the report
 }
 
 // ////////////////////////////////////////////////////////////////
@@ -59,15 +67,24 @@ pub struct Output {
     pub atomic_record_map: AtomicRecordMap,
     // pub rt_tx: mpsc::Sender<DM2Deck>,                     
     pub outputrunner_rx: mpsc::Receiver<DM2OutputRunner>,
+    pub archive: Archive,
+    pub clock: SharedClock,
+    pub vitals: Arc<Vitals>,
+    pub tick: Duration,                 // How often to check for reports that are due
 }
 
 impl Output {
     pub fn run(self) {
         let mut reports: Vec<ReportWrapper> = Vec::new();
         let mut lrb_map: HashMap<i32, SystemTime> = HashMap::new();
-        loop {
+
+        // A report that panics takes the loop down with it, start it again. The
+        // reports are kept, the one that panicked included.
+        while panic::catch_unwind(AssertUnwindSafe(|| loop {
+            self.vitals.output_alive();
+
             // Determine if there is any pending messages for this loop to act on
-            match self.outputrunner_rx.recv_timeout(Duration::from_secs(1)) {
+            match self.outputrunner_rx.recv_timeout(self.tick) {
                 Ok(msg) => {
                     match msg {
                         DM2OutputRunner::RegisterOutput(report) => {
@@ -77,14 +94,32 @@ impl Output {
                                 let r = ReportWrapper {
                                    report: report,
                                    freq: f,
-                                   last: UNIX_EPOCH, 
+                                   last: Duration::from_secs(0), 
+                                   archived: 0,
                                 };
                                 reports.push(r);
                             } else {
+                                Vitals::count(&self.vitals.report_init_failures);
                                 println!("Could not init report");
                             }
                         },
-                        DM2OutputRunner::StopOutput => {},
+                        DM2OutputRunner::TickOutput(tx) => {
+                            let _ = tx.send(self.run_reports(&mut reports, &mut lrb_map, false));
+                            continue;
+                        },
+                        // One last run of every report, whether it is due or
+                        // not, then end them. The first error goes back.
+                        DM2OutputRunner::StopOutput(tx) => {
+                            let mut res = self.run_reports(&mut reports, &mut lrb_map, true);
+                            for rw in reports.iter() {
+                                if let Err(e) = rw.report.end() {
+                                    Vitals::count(&self.vitals.report_errors);
+                                    res = res.and(Err(e));
+                                }
+                            }
+                            let _ = tx.send(res);
+                            break;
+                        },
                     }
                 },
                 Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {},
@@ -97,51 +132,83 @@ impl Output {
             // If there are no reports to use then there is no need to proceed
             if reports.is_empty() { continue };
 
-            // Run reports
-            let arm_copy = self.atomic_record_map.read().and_then(|arm| Ok(arm.clone()));
+            if let Err(e) = self.run_reports(&mut reports, &mut lrb_map, false) {
+                println!("This error {:?}", e);
+            }
+        })).is_err() {
+            Vitals::count(&self.vitals.output_restarts);
+        }
+    }
+
+    // Run the reports that are due, or all of them if forced, returning the
+    // first error any of them ran into
+    fn run_reports(&self, reports: &mut [ReportWrapper], lrb_map: &mut HashMap<i32, SystemTime>, force: bool) -> Result<(), TE> {
+        let mut res = Ok(());
 
-            // Hopefully at this point self.atomic_record_map has it's read lock released
-            // this may require further testing
-            if let Ok(arm) = arm_copy {
+        // Run reports
+        let arm_copy = self.atomic_record_map.read().and_then(|arm| Ok(arm.clone()));
+
+        // Hopefully at this point self.atomic_record_map has it's read lock released
+        // this may require further testing
+        if let Ok(arm) = arm_copy {
+            
+            // Iter through reports (These are wrapped with RecordWrapper, that adds 
+            // a layer of extra metric information needed to track LBR or Last Beat Record)
+            for rw in reports.iter_mut() {
                 
-                // Iter through reports (These are wrapped with RecordWrapper, that adds 
-                // a layer of extra metric information needed to track LBR or Last Beat Record)
-                for rw in reports.iter_mut() {
-                    
-                    // If report hasn't waited long enough to run again then no need to proceed
-                    if SystemTime::now() < rw.last.checked_add(rw.freq).unwrap_or(UNIX_EPOCH) { continue };
-                    
-                    // Update the lrb_map with new beats since last iteration, if any.
-                    for (id, record) in arm.iter() {
-                        match lrb_map.entry(*id) {
-                            std::collections::hash_map::Entry::Occupied(o) => {
-                                if !record.has_beat_since(Some(o.into_mut())) {
-                                    // In this scenario the record exist but there are no updates to report
-                                    continue
-                                }
-                            },
-                            std::collections::hash_map::Entry::Vacant(v) => {
-                                if let Some(timestamp) = record.raw_track.back() {
-                                    // Insert the new record to lrb
-                                    v.insert(*timestamp);
-                                }
-                            },
-                        }
+                // If report hasn't waited long enough to run again then no need to proceed
+                if !force && self.clock.monotonic() < rw.last + rw.freq { continue };
+                
+                // Update the lrb_map with new beats since last iteration, if any.
+                for (id, record) in arm.iter() {
 
-                        // Run the report
-                        match rw.report.run(record) {
-                            Ok(_) => {},
-                            Err(TE::NothingNewToReport) => {},
-                            Err(e) => {
-                                println!("This error {:?}", e);
-                            },
-                        }
+                    // Records that are still warming up are not reported on, nor
+                    // those whose task is gone. They reach the reports once
+                    // archived, see run_archived.
+                    if record.is_warming_up() || record.is_terminated() { continue };
+
+                    match lrb_map.entry(*id) {
+                        std::collections::hash_map::Entry::Occupied(o) => {
+                            if !record.has_beat_since(Some(o.into_mut())) {
+                                // In this scenario the record exist but there are no updates to report
+                                continue
+                            }
+                        },
+                        std::collections::hash_map::Entry::Vacant(v) => {
+                            if let Some(timestamp) = record.raw_track.back() {
+                                // Insert the new record to lrb
+                                v.insert(*timestamp);
+                            }
+                        },
                     }
 
-                    // Set the last timestamp this report was run
-                    rw.last = SystemTime::now();
+                    // Run the report
+                    match rw.report.run(record) {
+                        Ok(_) => {},
+                        Err(TE::NothingNewToReport) => {},
+                        Err(e) => {
+                            Vitals::count(&self.vitals.report_errors);
+                            res = res.and(Err(e));
+                        },
+                    }
                 }
-            };
-        }
+
+                // Hand over the records archived since the report last ran
+                if let Ok(archive) = self.archive.read() {
+                    let since = rw.archived;
+                    for archived in archive.iter().filter(|a| a.seq > since) {
+                        if let Err(e) = rw.report.run_archived(archived) {
+                            Vitals::count(&self.vitals.report_errors);
+                            res = res.and(Err(e));
+                        }
+                        rw.archived = archived.seq;
+                    }
+                }
+
+                // Set the last timestamp this report was run
+                rw.last = self.clock.monotonic();
+            }
+        };
+        res
     }
 }
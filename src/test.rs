@@ -146,7 +146,8 @@ mod tests {
         // Send beats to the deck
         let beat_delay_duration = Duration::from_secs(1_u64);
         if let Ok(b) = dj.spin_new(String::from("test_beat"))  {
-            let t = SystemTime::now();
+            // Beats from the future are rejected, so lay them out up to now
+            let t = SystemTime::now().checked_sub(beat_delay_duration * (number_of_beats + 1)).unwrap();
             for n in 1..number_of_beats {
                 let mut t_ = t.checked_add(beat_delay_duration * n ).unwrap();
                 if let Err(e) = b.from(flucuate_timestamp(&mut t_, beat_delay_duration)) {
@@ -174,7 +175,7 @@ mod tests {
 
         // Test get_avg
         for i in 0..tc {
-            n.add_beat(now.checked_add(Duration::from_secs(i * td)).unwrap());
+            n.add_beat(now.checked_add(Duration::from_secs(i * td)).unwrap()).unwrap();
         }
         assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::Optimal);
 
@@ -184,7 +185,7 @@ mod tests {
         assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::NotOnce);
 
         // Test one time
-        n.add_beat(now);
+        n.add_beat(now).unwrap();
         assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::OnlyOnce);
         assert!(!n.is_optimal());
         n.clear();
@@ -193,15 +194,17 @@ mod tests {
         let offset = 5;
         let offset_dur = Duration::from_secs(td + offset);
         for i in 0..tc {
-            n.add_beat(now.checked_add(Duration::from_secs(i * (td + offset))).unwrap());
+            n.add_beat(now.checked_add(Duration::from_secs(i * (td + offset))).unwrap()).unwrap();
         }
         assert_eq!(n.get_avg_diff().unwrap(), offset as i128 * 1000) ;
         assert_eq!(n.get_beat_diffs(None).unwrap(), vec![offset_dur; (tc - 1)  as usize]) ;
         assert_eq!(n.get_beat_diffs(n.raw_track.front()).unwrap(), vec![offset_dur; tc as usize - 1]) ;
 
-        // Test get_last
-        n.add_beat(now);
-        assert_eq!(n.raw_track.back().unwrap(), &now);
+        // Test get_last, a beat already on the track is rejected
+        assert!(matches!(n.add_beat(now), Err(TE::DuplicateBeat)));
+        let last = now.checked_add(Duration::from_secs(60)).unwrap();
+        n.add_beat(last).unwrap();
+        assert_eq!(n.raw_track.back().unwrap(), &last);
 
         // Does it still average?
         assert!(n.get_activity_rating().is_ok());
@@ -209,12 +212,1146 @@ mod tests {
         Ok(())
 
     }
+
+    #[test]
+    fn health_test() -> io::Result<()> {
+
+        let freq = Duration::from_secs(1);
+        let now = SystemTime::now();
+        let mut h = Health::new(now);
+
+        // Small wobbles under the late threshold do not move the state
+        assert!(h.observe(0.02, freq, now).is_none());
+        assert!(h.observe(0.08, freq, now).is_none());
+        assert_eq!(h.state, HealthState::Healthy);
+
+        // Crossing the enter threshold degrades, and it stays degraded until
+        // dropping under the exit threshold
+        let t = h.observe(0.12, freq, now).unwrap();
+        assert_eq!((t.from, t.to), (HealthState::Healthy, HealthState::Late));
+        assert!(h.observe(0.07, freq, now).is_none());
+        assert_eq!(h.state, HealthState::Late);
+
+        // Jumps straight to the worst state entered
+        assert_eq!(h.observe(6.0, freq, now).unwrap().to, HealthState::Dead);
+
+        // Steps down through Recovered rather than straight to Healthy
+        assert_eq!(h.observe(0.0, freq, now).unwrap().to, HealthState::Recovered);
+        assert!(h.observe(0.0, freq, now).is_none());
+        let later = now.checked_add(freq * 3).unwrap();
+        let t = h.observe(0.0, freq, later).unwrap();
+        assert_eq!((t.to, t.at), (HealthState::Healthy, later));
+        assert_eq!(h.since, later);
+        assert_eq!(h.transitions.len(), 4);
+
+        // Nonsense thresholds are refused
+        let mut t = HealthThresholds::default();
+        assert!(t.validate().is_ok());
+        t.settle = -1.0;
+        assert!(matches!(t.validate(), Err(TE::InvalidSetting(_))));
+        t.settle = 3.0;
+        t.late.exit = f32::NAN;
+        assert!(t.validate().is_err());
+        t.late = Threshold { enter: 0.1, exit: 0.2 };
+        assert!(t.validate().is_err());
+
+        // Or can't take the Deck down should they get through
+        h.thresholds.settle = f32::NAN;
+        assert_eq!(h.observe(6.0, freq, later).unwrap().to, HealthState::Dead);
+        assert_eq!(h.observe(0.0, freq, later).unwrap().to, HealthState::Recovered);
+        assert!(h.observe(0.0, freq, later.checked_add(freq * 100).unwrap()).is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn overdue_test() -> io::Result<()> {
+
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+
+        // Without an expected freq the default deadline can not be determined
+        n.add_beat(now.checked_sub(Duration::from_secs(5)).unwrap()).unwrap();
+        assert!(!n.is_overdue());
+
+        // An absolute deadline does not need one
+        n.set_deadline(Deadline::Absolute(Duration::from_secs(2)));
+        assert_eq!(n.get_overdue().unwrap().missed, 1);
+
+        // A healthy looking history is flagged as soon as the deadline passes
+        n.set_deadline(Deadline::default());
+        n.set_expected_freq(Duration::from_secs(1));
+        assert_eq!(n.get_overdue().unwrap().missed, 5);
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::Missing);
+        assert_eq!(n.update_health().unwrap().to, HealthState::Missing);
+
+        // A fresh beat clears it
+        n.add_beat(SystemTime::now()).unwrap();
+        assert!(n.get_overdue().is_none());
+
+        // A multiple that makes no sense is refused, and never taken as a deadline
+        assert!(matches!(Deadline::FreqMultiple(-1.0).validate(), Err(TE::InvalidSetting(_))));
+        assert!(Deadline::FreqMultiple(f32::NAN).validate().is_err());
+        n.set_deadline(Deadline::FreqMultiple(-1.0));
+        assert!(n.get_overdue().is_none());
+        n.set_deadline(Deadline::FreqMultiple(f32::MAX));
+        assert!(n.get_overdue().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tolerance_test() -> io::Result<()> {
+
+        // Beats that are consistently 5% late
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+        n.set_expected_freq(Duration::from_secs(10));
+        for i in 0..5 {
+            n.add_beat(now.checked_add(Duration::from_millis(i * 10_500)).unwrap()).unwrap();
+        }
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::NotOptimal);
+
+        n.set_tolerance(Tolerance::percent(10.0));
+        assert!(n.is_optimal());
+
+        n.set_tolerance(Tolerance::absolute(Duration::from_millis(400)));
+        assert!(!n.is_optimal());
+
+        // Being late matters more than being early
+        let tight = Margin::Percent(1.0);
+        let loose = Margin::Absolute(Duration::from_secs(1));
+        n.set_tolerance(Tolerance::lenient(BetterTo::GoUnder, tight, loose));
+        assert!(!n.is_optimal());
+        n.set_tolerance(Tolerance::lenient(BetterTo::GoOver, tight, loose));
+        assert!(n.is_optimal());
+
+        // Margins that make no sense are refused, and can't panic if set anyway
+        assert!(matches!(Tolerance::percent(-5.0).validate(), Err(TE::InvalidSetting(_))));
+        assert!(Tolerance::percent(f32::NAN).validate().is_err());
+        n.set_tolerance(Tolerance::percent(-5.0));
+        assert!(!n.is_optimal());
+        n.set_tolerance(Tolerance::absolute(Duration::MAX));
+        assert!(n.is_optimal());
+
+        Ok(())
+    }
+
+    #[test]
+    fn stats_test() -> io::Result<()> {
+
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+        assert!(n.get_interval_stats().is_none());
+
+        // Intervals of 1, 2, 3 and 4 seconds
+        for s in [0, 1, 3, 6, 10].iter() {
+            n.add_beat(now.checked_add(Duration::from_secs(*s)).unwrap()).unwrap();
+        }
+        let stats = n.get_interval_stats().unwrap();
+        let secs = Duration::from_secs;
+        assert_eq!(stats.count, 4);
+        assert_eq!((stats.min, stats.max), (secs(1), secs(4)));
+        assert_eq!(stats.mean, Duration::from_millis(2500));
+        assert_eq!((stats.p50, stats.p90, stats.p99), (secs(2), secs(4), secs(4)));
+        assert_eq!(stats.jitter, secs(1));
+        assert!((stats.stddev.as_secs_f64() - 1.25_f64.sqrt()).abs() < 1e-6);
+        assert!((stats.ewma.as_secs_f64() - 2.048).abs() < 1e-6);
+
+        Ok(())
+    }
+
+    #[test]
+    fn guess_freq_test() -> io::Result<()> {
+
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+        n.infer_freq();
+        assert_eq!(n.get_freq_confidence(), ConfidenceLevel::Nil);
+
+        // Few and varied intervals give little confidence
+        for s in [0, 2, 3, 6].iter() {
+            n.add_beat(now.checked_add(Duration::from_secs(*s)).unwrap()).unwrap();
+        }
+        n.infer_freq();
+        assert_eq!(n.inferred_freq, Some((Duration::from_secs(2), ConfidenceLevel::Maybe)));
+        assert_eq!(n.freq, Duration::from_secs(2));
+
+        // Many steady intervals give a lot
+        n.clear();
+        for i in 0..12 {
+            n.add_beat(now.checked_add(Duration::from_secs(i * 4)).unwrap()).unwrap();
+        }
+        n.infer_freq();
+        assert_eq!(n.inferred_freq, Some((Duration::from_secs(4), ConfidenceLevel::Very)));
+        assert_eq!(n.get_freq_confidence(), ConfidenceLevel::Very);
+
+        // A user defined freq always takes precedence
+        n.set_expected_freq(Duration::from_secs(5));
+        n.infer_freq();
+        assert_eq!(n.freq, Duration::from_secs(5));
+        assert_eq!(n.get_freq_confidence(), ConfidenceLevel::UserDefined);
+        assert_eq!(n.inferred_freq.unwrap().0, Duration::from_secs(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn low_confidence_deadline_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let mut n = Record::new_with_clock("foo".to_string(), 0, clock.shared());
+
+        // A few jittery intervals only give a Maybe
+        n.add_stamp(clock.stamp()).unwrap();
+        for s in [2, 1, 3].iter() {
+            clock.advance(Duration::from_secs(*s));
+            n.add_stamp(clock.stamp()).unwrap();
+        }
+        n.infer_freq();
+        assert_eq!(n.get_freq_confidence(), ConfidenceLevel::Maybe);
+        assert!(n.get_confident_freq().is_none());
+
+        // Which isn't enough to call the record late or overdue
+        clock.advance(Duration::from_secs(5));
+        assert!(n.get_lateness().is_none());
+        assert!(!n.is_overdue());
+        assert!(n.update_health().is_none());
+        assert_eq!(n.get_health_state().0, HealthState::Healthy);
+
+        // An expected freq is
+        n.set_expected_freq(Duration::from_secs(2));
+        assert_eq!(n.get_overdue().unwrap().missed, 2);
+        assert_eq!(n.update_health().unwrap().to, HealthState::Missing);
+
+        Ok(())
+    }
+
+    #[test]
+    fn schedule_test() -> io::Result<()> {
+
+        use std::time::UNIX_EPOCH;
+        let at = |secs: u64| UNIX_EPOCH.checked_add(Duration::from_secs(secs)).unwrap();
+
+        // Monday 2021-03-01 00:00 UTC
+        let monday = 1_614_556_800;
+        let daily = Schedule::parse("0 2 * * *").unwrap();
+        assert_eq!(daily.next_after(at(monday)), Some(at(monday + 2 * 3600)));
+
+        // Every 15 minutes on weekdays skips over the weekend
+        let weekdays = Schedule::parse("*/15 * * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(at(monday)), Some(at(monday + 15 * 60)));
+        let friday_late = monday + 4 * 86_400 + 23 * 3600 + 50 * 60;
+        assert_eq!(weekdays.next_after(at(friday_late)), Some(at(monday + 7 * 86_400)));
+
+        assert!(Schedule::parse("61 * * * *").is_err());
+        assert!(Schedule::parse("* * *").is_err());
+
+        // Every run whose grace window has closed without a beat is flagged
+        let mut n = Record::new("foo".to_string(), 0);
+        n.set_schedule(Schedule::parse("* * * * *").unwrap(), Duration::from_secs(10));
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let first_run = at((now / 60 - 5) * 60);
+        n.scheduled.as_mut().unwrap().next_run = Some(first_run);
+        n.add_beat(first_run + Duration::from_secs(2)).unwrap();
+
+        assert!(n.check_schedule() >= 4);
+        let missed = n.get_missed_runs();
+        assert!(!missed.contains(&first_run));
+        assert!(missed.contains(&(first_run + Duration::from_secs(60))));
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::Missing);
+
+        // A run that beat still moves the next run on in the snapshot
+        let clock = MockClock::new(at(monday));
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beat = dj.spin_new(String::from("cron")).unwrap();
+        assert!(dj.set_schedule(beat.id, "* * * * *", Duration::from_secs(10)).is_ok());
+        assert!(dj.tick().is_ok());
+        let next_run = || dj.with_record(beat.id, |r| r.scheduled.as_ref().unwrap().next_run).unwrap();
+        assert_eq!(next_run(), Some(at(monday + 60)));
+        clock.advance(Duration::from_secs(60));
+        assert!(beat.now().is_ok());
+        assert!(wait_for(|| dj.with_record(beat.id, |r| r.raw_track.len() == 1).unwrap()));
+        clock.advance(Duration::from_secs(15));
+        assert!(dj.tick().is_ok());
+        assert_eq!(next_run(), Some(at(monday + 120)));
+        assert!(dj.get_missed_runs(beat.id).unwrap().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn pattern_test() -> io::Result<()> {
+
+        // Bursts of three quick beats then a long pause, starting mid burst
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+        let mut t = now;
+        for ms in [100, 100, 2000, 100, 100, 2000, 100, 100].iter() {
+            n.add_beat(t).unwrap();
+            t += Duration::from_millis(*ms);
+        }
+        n.add_beat(t).unwrap();
+        n.set_expected_freq(Duration::from_millis(100));
+        n.set_deadline(Deadline::Absolute(Duration::from_secs(60)));
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::NotOptimal);
+
+        n.set_expected_pattern(vec![100, 2000, 100]);
+        let beats = n.get_pattern_beats();
+        assert_eq!(beats.len(), 8);
+        assert!(beats.iter().all(|b| b.actual == b.expected));
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::Optimal);
+        assert_eq!(n.get_lateness(), Some(0.0));
+
+        // The upcoming beat is expected after the long pause
+        let mut iter = n.raw_track.into_iter().pattern(&n.get_pattern());
+        iter.by_ref().for_each(drop);
+        assert_eq!(iter.expected_next(), Some(Duration::from_millis(2000)));
+
+        // A quick beat out of place breaks the pattern
+        n.add_beat(t + Duration::from_millis(100)).unwrap();
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::NotOptimal);
+
+        Ok(())
+    }
+
+    #[test]
+    fn warmup_test() -> io::Result<()> {
+
+        // An overdue record is left alone while it warms up
+        let now = SystemTime::now();
+        let mut n = Record::new("foo".to_string(), 0);
+        n.set_expected_freq(Duration::from_secs(1));
+        n.add_beat(now.checked_sub(Duration::from_secs(10)).unwrap()).unwrap();
+        n.set_warmup(Duration::from_secs(60));
+        assert!(n.is_warming_up());
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::WarmingUp);
+        assert!(n.update_health().is_none());
+
+        // Counted from deployment
+        n.set_deployment(now.checked_sub(Duration::from_secs(120)).unwrap());
+        assert!(!n.is_warming_up());
+        assert_eq!(n.get_activity_rating().unwrap(), ActivityRating::Missing);
+        assert_eq!(n.update_health().unwrap().to, HealthState::Dead);
+
+        // One too long to count never ends
+        n.set_warmup(Duration::MAX);
+        assert!(n.is_warming_up());
+
+        Ok(())
+    }
+
+    #[test]
+    fn mock_clock_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000));
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beat = dj.spin_new(String::from("mocked")).unwrap();
+        let freq = Duration::from_secs(60);
+        assert!(beat.set_expected_freq(freq).is_ok());
+
+        // Five minutes of beats without the wait
+        for _ in 0..5 {
+            assert!(beat.now().is_ok());
+            clock.advance(freq);
+        }
+        assert!(wait_for(|| dj.get_record(beat.id).is_ok_and(|r| r.raw_track.len() == 5)));
+        let record = dj.get_record(beat.id).unwrap();
+        assert_eq!(record.get_beat_diffs(None).unwrap(), vec![freq; 4]);
+        assert!(record.is_optimal());
+
+        // Or the hour of a dead task
+        clock.advance(Duration::from_secs(3600));
+        assert_eq!(record.get_activity_rating().unwrap(), ActivityRating::Missing);
+        assert_eq!(record.get_overdue().unwrap().missed, 61);
+        assert!(dj.tick().is_ok());
+        assert_eq!(dj.get_health_state(beat.id).unwrap().0, HealthState::Dead);
+
+        Ok(())
+    }
+
+    #[test]
+    fn clock_jump_test() -> io::Result<()> {
+
+        let sec = Duration::from_secs(1);
+        let clock = MockClock::new(SystemTime::now());
+        let mut n = Record::new_with_clock("foo".to_string(), 0, clock.shared());
+        for _ in 0..3 {
+            n.add_stamp(clock.stamp()).unwrap();
+            clock.advance(sec);
+        }
+
+        // NTP steps the wall clock back an hour, the intervals are unaffected
+        clock.step_wall(clock.now() - Duration::from_secs(3600));
+        for _ in 0..3 {
+            n.add_stamp(clock.stamp()).unwrap();
+            clock.advance(sec);
+        }
+        assert_eq!(n.get_beat_diffs(None).unwrap(), vec![sec; 5]);
+        assert_eq!(n.clock_jumps, 1);
+
+        // A beat that arrives late is slotted in where it belongs
+        let first = n.raw_track.0[0];
+        let half = Duration::from_millis(500);
+        n.add_stamp(Stamp { mono: first.mono + half, wall: first.wall + half }).unwrap();
+        assert_eq!(n.out_of_order, 1);
+        assert_eq!(n.raw_track.0[1].mono, first.mono + half);
+        assert_eq!(n.get_beat_diffs(None).unwrap()[..3], [half, half, sec]);
+
+        // One already on the track is rejected
+        assert!(matches!(n.add_stamp(first), Err(TE::DuplicateBeat)));
+        assert_eq!(n.raw_track.len(), 7);
+
+        // Historical beats are placed relative to now once the wall clock is off
+        let ago = clock.now() - Duration::from_secs(10);
+        assert_eq!(clock.stamp_at(ago).mono, clock.monotonic() - Duration::from_secs(10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn beat_from_test() -> io::Result<()> {
+
+        let dj = TheDJ::init().unwrap();
+        let beat = dj.spin_new(String::from("historical")).unwrap();
+        let now = SystemTime::now();
+        let ago = |secs| now.checked_sub(Duration::from_secs(secs)).unwrap();
+
+        // Beats from the past are accepted in any order and kept sorted
+        assert!(beat.from(ago(5)).is_ok());
+        assert!(beat.from(ago(15)).is_ok());
+        assert!(beat.from(ago(10)).is_ok());
+        let record = dj.get_record(beat.id).unwrap();
+        assert_eq!(record.get_beats(None).unwrap(), vec![ago(15), ago(10), ago(5)]);
+
+        // But not twice, from the future or when too old
+        assert!(matches!(beat.from(ago(10)), Err(TE::DuplicateBeat)));
+        assert!(matches!(beat.from(now + Duration::from_secs(3600)), Err(TE::FutureBeat)));
+        assert!(beat.set_max_beat_age(Some(Duration::from_secs(60))).is_ok());
+        assert!(matches!(beat.from(ago(120)), Err(TE::StaleBeat)));
+        assert!(beat.from(ago(30)).is_ok());
+        assert_eq!(dj.get_record(beat.id).unwrap().raw_track.len(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn span_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beat = dj.spin_new(String::from("spanned")).unwrap();
+        let (work, sleep) = (Duration::from_millis(200), Duration::from_millis(800));
+
+        // A loop working for 200ms then sleeping for 800ms
+        for i in 0..5 {
+            let span = beat.span().unwrap();
+            clock.advance(work);
+            if i % 2 == 0 { drop(span) } else { span.finish().unwrap() };
+            clock.advance(sleep);
+        }
+        assert!(wait_for(|| dj.get_record(beat.id).is_ok_and(|r| r.runs.len() == 5)));
+        let record = dj.get_record(beat.id).unwrap();
+        assert_eq!(record.get_run_stats().unwrap().mean, work);
+        assert_eq!(record.get_interval_stats().unwrap().mean, work + sleep);
+        assert!(record.open_span.is_none());
+
+        // A finish without a start is ignored
+        let mut n = Record::new_with_clock("foo".to_string(), 0, clock.shared());
+        assert!(n.end_span(clock.stamp()).is_none());
+        assert!(n.get_run_stats().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn fields_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beat = dj.spin_new(String::from("worker")).unwrap();
+
+        for depth in 1..=3 {
+            assert!(beat.now_with(&[("queue depth", depth as f64), ("processed", 10.0)]).is_ok());
+            clock.advance(Duration::from_secs(1));
+        }
+        assert!(beat.now().is_ok());
+        assert!(wait_for(|| dj.get_record(beat.id).is_ok_and(|r| r.raw_track.len() == 4)));
+        assert_eq!(dj.get_field(beat.id, "queue depth").unwrap(), Some(3.0));
+        assert_eq!(dj.get_field(beat.id, "missing").unwrap(), None);
+
+        let record = dj.get_record(beat.id).unwrap();
+        assert_eq!(record.get_field_values("queue depth").unwrap(), vec![1.0, 2.0, 3.0]);
+
+        // Exported along with the beat they were sent with
+        let beats = record.get_beats(None).unwrap();
+        assert_eq!(record.get_fields_at(beats[0]), vec![("processed", 10.0), ("queue depth", 1.0)]);
+        assert!(record.get_fields_at(beats[3]).is_empty());
+        let line = crate::output::influxdb::line("m", &record, beats[1]);
+        assert!(line.starts_with("m,beatname=worker expected=1,processed=10,queue\\ depth=2 "));
+
+        // Names the export writes itself are refused
+        assert!(matches!(beat.now_with(&[("expected", 1.0)]), Err(TE::ReservedField(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn outcome_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beat = dj.spin_new(String::from("flaky")).unwrap();
+        let sec = Duration::from_secs(1);
+        assert!(beat.set_expected_freq(sec).is_ok());
+        let rating = |len| {
+            assert!(wait_for(|| dj.get_record(beat.id).is_ok_and(|r| r.raw_track.len() == len)));
+            dj.get_record(beat.id).unwrap().get_activity_rating().unwrap()
+        };
+
+        // On time and working
+        for _ in 0..4 {
+            assert!(beat.now().is_ok());
+            clock.advance(sec);
+        }
+        assert_eq!(rating(4), ActivityRating::Optimal);
+
+        // On time, but not working as it should
+        assert!(beat.degraded("slow upstream").is_ok());
+        clock.advance(sec);
+        assert_eq!(rating(5), ActivityRating::Degraded);
+
+        // On time, but failing every iteration
+        for _ in 0..5 {
+            assert!(beat.fail("connection refused").is_ok());
+            clock.advance(sec);
+        }
+        assert_eq!(rating(10), ActivityRating::Failing);
+        assert_eq!(dj.get_success_ratio(beat.id).unwrap(), Some(0.4));
+        let record = dj.get_record(beat.id).unwrap();
+        assert_eq!(record.get_failure_ratio(), Some(0.5));
+        assert_eq!(record.get_last_failure().unwrap().1, "connection refused");
+        assert_eq!(record.get_health_state().0, HealthState::Missing);
+
+        // Back to working
+        assert!(beat.now().is_ok());
+        clock.advance(sec);
+        assert_eq!(rating(11), ActivityRating::Optimal);
+        assert_eq!(dj.get_health_state(beat.id).unwrap().0, HealthState::Recovered);
+
+        // Beats from a timestamp count as working too
+        clock.advance(sec);
+        assert!(beat.from(clock.now()).is_ok());
+        assert_eq!(dj.get_success_ratio(beat.id).unwrap(), Some(0.5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn termination_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let state = |id| dj.get_health_state(id).map(|h| h.0).ok();
+
+        // Finished, dropped by accident and panicked
+        let completed = dj.spin_new(String::from("completed")).unwrap();
+        let abandoned = dj.spin_new(String::from("abandoned")).unwrap();
+        let panicked = dj.spin_new(String::from("panicked")).unwrap();
+        let ids = (completed.id, abandoned.id, panicked.id);
+        assert!(completed.now().is_ok());
+        assert!(completed.complete().is_ok());
+        drop(abandoned);
+        assert!(std::thread::spawn(move || {
+            let _beat = panicked;
+            panic!("Task panicked");
+        }).join().is_err());
+
+        // The evidence is kept
+        assert!(wait_for(|| state(ids.0) == Some(HealthState::Completed)));
+        assert!(wait_for(|| state(ids.1) == Some(HealthState::Abandoned)));
+        assert!(wait_for(|| state(ids.2) == Some(HealthState::Panicked)));
+        assert_eq!(dj.get_record(ids.0).unwrap().raw_track.len(), 1);
+        assert_eq!(dj.get_roster().unwrap().len(), 3);
+        assert!(dj.get_roster_actives().is_err());
+
+        // Until the retention period is up
+        assert!(dj.set_retention(Duration::from_secs(60)).is_ok());
+        clock.advance(Duration::from_secs(61));
+        assert!(wait_for(|| dj.get_roster().is_err()));
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    pub struct ArchiveReport {ran: Arc<Mutex<Vec<String>>>, archived: Arc<Mutex<Vec<String>>>}
+
+    impl Report for ArchiveReport {
+        fn duration(&self)        -> Result<Duration> {Ok(Duration::from_secs(0))}
+        fn init(&self)            -> Result<()> { Ok(()) }
+        fn run(&mut self, record: &Record) -> Result<()> {
+            self.ran.lock().unwrap().push(record.name.clone());
+            Ok(())
+        }
+        fn end(&self)             -> Result<()> { Ok(()) }
+        fn run_archived(&mut self, archived: &Archived) -> Result<()> {
+            self.archived.lock().unwrap().push(archived.record.name.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn archive_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let (ran, reported) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+        let dj = TheDJ::builder()
+            .clock(clock.shared())
+            .output_tick(Duration::from_secs(3600))
+            .report(Box::new(ArchiveReport{ran: ran.clone(), archived: reported.clone()}))
+            .build()
+            .unwrap();
+        assert!(dj.tick().is_ok());
+
+        // Completed and purged once the retention period is up
+        let beat = dj.spin_new(String::from("done")).unwrap();
+        let id = beat.id;
+        for _ in 0..3 {
+            assert!(beat.now().is_ok());
+            clock.advance(Duration::from_secs(1));
+        }
+        assert!(beat.complete().is_ok());
+        assert!(dj.set_retention(Duration::from_secs(10)).is_ok());
+        assert!(wait_for(|| dj.get_health_state(id).is_ok_and(|h| h.0 == HealthState::Completed)));
+
+        // Which is no longer run through the reports, nor counted as active
+        assert!(dj.tick().is_ok());
+        assert!(ran.lock().unwrap().is_empty());
+        assert!(dj.get_roster_actives().is_err());
+        clock.advance(Duration::from_secs(11));
+        assert!(wait_for(|| dj.get_archived(id).is_ok()));
+        let archived = dj.get_archived(id).unwrap();
+        assert_eq!(archived.cause, HealthState::Completed);
+        assert_eq!(archived.record.raw_track.len(), 3);
+        assert!(archived.ended < archived.archived);
+        assert!(dj.get_record(id).is_err());
+
+        // Unregistered straight into the archive, reusing the id
+        let beat = dj.spin_new(String::from("removed")).unwrap();
+        assert_eq!(beat.id, id);
+        assert!(dj.unregister(beat.id).is_ok());
+        assert!(wait_for(|| dj.get_archive().is_ok_and(|a| a.len() == 2)));
+        assert_eq!(dj.get_archived(id).unwrap().cause, HealthState::Unregistered);
+
+        // Reports see each archived record once
+        assert!(dj.tick().is_ok());
+        assert_eq!(*reported.lock().unwrap(), vec!["done", "removed"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn handle_test() -> io::Result<()> {
+
+        let dj = TheDJ::init().unwrap();
+        let first = dj.spin_new(String::from("first")).unwrap();
+
+        // A Beat left behind by the first task, such as one moved into a thread
+        let stale = Beat {
+            sender: first.sender.clone(),
+            id: first.id,
+            generation: first.generation,
+            clock: first.clock.clone(),
+            ring: first.ring.clone(),
+            reply_timeout: first.reply_timeout,
+            completed: false,
+        };
+        assert!(dj.unregister(first.id).is_ok());
+        drop(first);
+        assert!(wait_for(|| dj.get_roster().is_err()));
+
+        // The id is reused by the next record, with a new generation
+        let second = dj.spin_new(String::from("second")).unwrap();
+        assert_eq!(second.id, stale.id);
+        assert_eq!(second.generation, stale.generation + 1);
+        assert!(second.now().is_ok());
+
+        // Nothing from the stale Beat reaches it
+        assert!(stale.now().is_ok());
+        let ago = SystemTime::now().checked_sub(Duration::from_secs(5)).unwrap();
+        assert!(matches!(stale.from(ago), Err(TE::StaleHandle)));
+        assert!(stale.sender.send(DM2Deck::Deregistration(stale.handle())).is_ok());
+        drop(stale);
+        assert!(second.from(ago).is_ok());
+        let record = dj.get_record(second.id).unwrap();
+        assert_eq!(record.name, "second");
+        assert_eq!(record.raw_track.len(), 2);
+        assert_eq!(dj.get_health_state(second.id).unwrap().0, HealthState::Healthy);
+
+        Ok(())
+    }
+
+    #[test]
+    fn ring_test() -> io::Result<()> {
+
+        // Writers a lap ahead overwrite the oldest beats
+        let ms = Duration::from_millis;
+        let ring = BeatRing::new(4);
+        (1..=6).for_each(|i| ring.push(ms(i)));
+        let mut drained = Vec::new();
+        assert_eq!(ring.drain(|mono| drained.push(mono)), 2);
+        assert_eq!(drained, vec![ms(3), ms(4), ms(5), ms(6)]);
+        assert_eq!(ring.drain(|_| panic!("Drained twice")), 0);
+
+        // Every beat from several threads is either drained or counted as lost
+        let ring = Arc::new(BeatRing::new(64));
+        let writers = (0..4).map(|_| {
+            let ring = ring.clone();
+            std::thread::spawn(move || (0..10_000).for_each(|i| ring.push(ms(i))))
+        }).collect::<Vec<_>>();
+        let (mut read, mut lost) = (0, 0);
+        while writers.iter().any(|w| !w.is_finished()) {
+            lost += ring.drain(|_| read += 1);
+        }
+        lost += ring.drain(|_| read += 1);
+        assert_eq!(read + lost, 40_000);
+
+        // Writers a lap apart on the same slot don't tear each other's beats,
+        // which would show up as the same beat drained twice
+        let ring = Arc::new(BeatRing::new(2));
+        let writers = (0..8u64).map(|w| {
+            let ring = ring.clone();
+            std::thread::spawn(move || (0..10_000).for_each(|i| ring.push(ms(w * 10_000 + i))))
+        }).collect::<Vec<_>>();
+        let (mut read, mut lost) = (Vec::new(), 0);
+        while writers.iter().any(|w| !w.is_finished()) {
+            lost += ring.drain(|mono| read.push(mono));
+        }
+        lost += ring.drain(|mono| read.push(mono));
+        assert_eq!(read.len() as u64 + lost, 80_000);
+        read.sort();
+        read.dedup();
+        assert_eq!(read.len() as u64 + lost, 80_000);
+
+        // A tight loop through the DJ
+        let dj = TheDJ::init().unwrap();
+        let beat = dj.spin_new(String::from("tight")).unwrap();
+        for _ in 0..500 {
+            assert!(beat.now().is_ok());
+        }
+        assert!(wait_for(|| dj.get_record(beat.id).is_ok_and(|r| r.raw_track.len() == BEAT_CAP)));
+        assert_eq!(dj.get_record(beat.id).unwrap().lost_beats, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn arm_sharing_test() -> io::Result<()> {
+
+        // Run the Deck on its own
+        let clock = MockClock::new(SystemTime::now());
+        let (deck_tx, deck_rx) = channel();
+        let (dj_tx, dj_rx) = channel();
+        let (output_tx, _output_rx) = channel();
+        Deck::run(deck_rx, dj_tx, output_tx, clock.shared());
+        assert!(deck_tx.send(DM2Deck::Init()).is_ok());
+        let arm = match dj_rx.recv() {
+            Ok(DM2DJ::ARM(arm)) => arm,
+            _ => panic!("No ARM"),
+        };
+        let _archive = dj_rx.recv();
+        let mut handles = Vec::new();
+        for name in ["busy", "idle"].iter() {
+            let ring = Arc::new(BeatRing::new(RING_CAP));
+            let (reply_tx, reply_rx) = channel();
+            assert!(deck_tx.send(DM2Deck::Registration(name.to_string(), ring, None, reply_tx)).is_ok());
+            match reply_rx.recv() {
+                Ok(Ok(handle)) => handles.push(handle),
+                _ => panic!("No ID"),
+            }
+        }
+        let snapshot = |id| arm.read().unwrap().get(&id).cloned().unwrap();
+
+        // Only the record that beat is replaced, the other is still shared
+        let (busy, idle) = (snapshot(handles[0].id), snapshot(handles[1].id));
+        assert!(deck_tx.send(DM2Deck::Ping(handles[0], clock.stamp())).is_ok());
+        assert!(wait_for(|| snapshot(handles[0].id).raw_track.len() == 1));
+        assert!(!Arc::ptr_eq(&busy, &snapshot(handles[0].id)));
+        assert!(Arc::ptr_eq(&idle, &snapshot(handles[1].id)));
+        assert!(busy.raw_track.is_empty());
+
+        // Removed records are dropped from the ARM
+        assert!(deck_tx.send(DM2Deck::Deregistration(handles[1])).is_ok());
+        assert!(wait_for(|| arm.read().unwrap().len() == 1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn record_access_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let dj = TheDJ::init_with_clock(clock.shared(), false).unwrap();
+        let beats = (0..3).map(|i| dj.spin_new(format!("beat_{}", i)).unwrap()).collect::<Vec<Beat>>();
+        for _ in 0..5 {
+            beats.iter().for_each(|b| assert!(b.now().is_ok()));
+            clock.advance(Duration::from_secs(1));
+        }
+        let id = beats[0].id;
+        assert!(wait_for(|| dj.with_records(|r| r.raw_track.len()).is_ok_and(|l| l == vec![5; 3])));
+
+        // The same snapshot until the record changes
+        let arc = dj.get_record_arc(id).unwrap();
+        assert!(Arc::ptr_eq(&arc, &dj.get_record_arc(id).unwrap()));
+        assert!(beats[0].now().is_ok());
+        assert!(wait_for(|| dj.with_record(id, |r| r.raw_track.len() == 6).unwrap()));
+        assert_eq!(arc.raw_track.len(), 5);
+
+        // Queries without copying the record
+        assert_eq!(dj.with_record(id, |r| r.get_interval_stats().unwrap().count).unwrap(), 5);
+        assert_eq!(dj.with_record(id, |r| r.name.clone()).unwrap(), "beat_0");
+        assert!(matches!(dj.with_record(999, |r| r.id), Err(TE::MissingRecord)));
+        let mut names = dj.with_records(|r| r.name.clone()).unwrap();
+        names.sort();
+        assert_eq!(names, vec!["beat_0", "beat_1", "beat_2"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_spin_test() -> io::Result<()> {
+
+        fn shareable<T: Send + Sync + Clone>() {}
+        shareable::<TheDJ>();
+
+        // Every thread gets back the Beat it asked for
+        let dj = TheDJ::init().unwrap();
+        let threads = (0..8).map(|t| {
+            let dj = dj.clone();
+            std::thread::spawn(move || {
+                (0..10).map(|i| {
+                    let name = format!("beat_{}_{}", t, i);
+                    let beat = dj.spin_new(name.clone()).unwrap();
+                    (beat, name)
+                }).collect::<Vec<(Beat, String)>>()
+            })
+        }).collect::<Vec<_>>();
+        let beats = threads.into_iter().flat_map(|t| t.join().unwrap()).collect::<Vec<(Beat, String)>>();
+        let mut ids = beats.iter().map(|(b, _)| b.id).collect::<Vec<i32>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 80);
+        for (beat, name) in beats.iter() {
+            assert_eq!(&dj.with_record(beat.id, |r| r.name.clone()).unwrap(), name);
+        }
+
+        // A registration nobody waits for is rolled back. The Deck may still
+        // beat the timeout now and then, those Beats are kept.
+        let mut dj = TheDJ::init().unwrap();
+        dj.set_reply_timeout(Duration::from_nanos(1));
+        let mut kept = Vec::new();
+        let mut timed_out = 0;
+        for i in 0..100 {
+            match dj.spin_new(format!("late_{}", i)) {
+                Ok(beat) => kept.push(beat),
+                Err(TE::ChanRecvTimeout(_)) => timed_out += 1,
+                Err(e) => panic!("{:?}", e),
+            }
+        }
+        assert!(timed_out > 0);
+        let mut ids = kept.iter().map(|b| b.id).collect::<Vec<i32>>();
+        ids.sort();
+        assert!(wait_for(|| {
+            let mut roster = dj.get_roster().unwrap_or_default();
+            roster.sort();
+            roster == ids
+        }));
+        dj.set_reply_timeout(REPLY_TIMEOUT);
+        assert!(dj.spin_new(String::from("on time")).is_ok());
+
+        Ok(())
+    }
+
+    #[test]
+    fn async_test() -> io::Result<()> {
+
+        fn sendable<T: Send>(_: &T) {}
+        let dj = TheDJ::init().unwrap();
+        sendable(&dj.spin_new_async(String::from("send")));
+        sendable(&dj.wait_for_beats(1, Duration::from_secs(1)));
+
+        smol::block_on(async {
+            let beat = dj.spin_new_async(String::from("async")).await.unwrap();
+            assert_eq!(dj.with_record(beat.id, |r| r.name.clone()).unwrap(), "async");
+            assert!(matches!(dj.spin_new_async(String::new()).await, Err(TE::RegisterFail(_))));
+            let long = dj.spin_new_async_with_track_len(String::from("long"), 8).await.unwrap();
+            assert_eq!(dj.with_record(long.id, |r| r.track_len).unwrap(), 8);
+            assert!(matches!(dj.spin_new_async_with_track_len(String::from("empty"), 0).await, Err(TE::RegisterFail(_))));
+
+            // Woken by the Deck rather than polling
+            let waiting = dj.wait_for_beats(1, Duration::from_secs(5));
+            assert!(beat.now().is_ok());
+            assert!(waiting.await.is_ok());
+
+            // Timeouts don't need a timer from the runtime
+            let start = std::time::Instant::now();
+            assert!(matches!(dj.wait_for_beats(2, Duration::from_millis(100)).await, Err(TE::NothingNewToReport)));
+            assert!(start.elapsed() < Duration::from_secs(1));
+
+            let id = beat.id;
+            assert!(beat.complete().is_ok());
+            assert!(dj.wait_for_state(id, HealthState::Completed, Duration::from_secs(5)).await.is_ok());
+            assert!(matches!(dj.wait_for_state(999, HealthState::Healthy, Duration::from_secs(5)).await, Err(TE::MissingRecord)));
+        });
+
+        // Nor a particular executor
+        let beat = futures::executor::block_on(dj.spin_new_async(String::from("futures"))).unwrap();
+        assert!(beat.now().is_ok());
+        assert!(futures::executor::block_on(dj.wait_for_beats(1, Duration::from_secs(5))).is_ok());
+
+        Ok(())
+    }
+
+    #[derive(Debug)]
+    pub struct ShutdownReport {seen: Arc<Mutex<Vec<usize>>>, ended: Arc<Mutex<bool>>, fail: bool}
+
+    impl Report for ShutdownReport {
+        fn duration(&self)        -> Result<Duration> {Ok(Duration::from_secs(3600))}
+        fn init(&self)            -> Result<()> { Ok(()) }
+        fn run(&mut self, record: &Record) -> Result<()> {
+            self.seen.lock().unwrap().push(record.raw_track.len());
+            Ok(())
+        }
+        fn end(&self)             -> Result<()> {
+            *self.ended.lock().unwrap() = true;
+            if self.fail { Err(TE::MaximumConfusion) } else { Ok(()) }
+        }
+    }
+
+    #[test]
+    fn shutdown_test() -> io::Result<()> {
+
+        let dj = TheDJ::init_with_reporting().unwrap();
+        let (seen, ended) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(false)));
+        let report = ShutdownReport{seen: seen.clone(), ended: ended.clone(), fail: false};
+        assert!(dj.add_report(Box::new(report)).is_ok());
+        let beat = dj.spin_new(String::from("last")).unwrap();
+        for _ in 0..5 {
+            assert!(beat.now().is_ok());
+        }
+        let removed = dj.spin_new(String::from("removed")).unwrap();
+        assert!(dj.unregister(removed.id).is_ok());
+
+        // A clone that keeps sending doesn't keep the Deck from stopping
+        let flood = dj.spin_new(String::from("flood")).unwrap();
+        let flooding = std::thread::spawn(move || while flood.set_warmup(Duration::from_secs(1)).is_ok() {});
+
+        // The beats still in the ring make it into the final report
+        let other = dj.clone();
+        assert!(dj.shutdown(Duration::from_secs(5)).is_ok());
+        assert!(flooding.join().is_ok());
+        assert_eq!(dj.get_record(beat.id).unwrap().raw_track.len(), 5);
+        assert!(seen.lock().unwrap().contains(&5));
+        assert!(*ended.lock().unwrap());
+
+        // Nothing is taken afterwards, by any clone
+        assert!(matches!(beat.now(), Err(TE::ShutDown)));
+        assert!(matches!(removed.now(), Err(TE::ShutDown)));
+        assert!(matches!(beat.set_warmup(Duration::from_secs(1)), Err(TE::ShutDown)));
+        assert!(matches!(beat.from(SystemTime::now()), Err(TE::ShutDown)));
+        assert!(matches!(beat.span(), Err(TE::ShutDown)));
+        assert!(matches!(dj.unregister(beat.id), Err(TE::ShutDown)));
+        assert!(matches!(dj.set_warmup(beat.id, Duration::from_secs(1)), Err(TE::ShutDown)));
+        assert!(matches!(dj.spin_new(String::from("late")), Err(TE::ShutDown)));
+        assert!(matches!(other.shutdown(Duration::from_secs(5)), Err(TE::ShutDown)));
+
+        // Nor is anyone left waiting on the Deck
+        let start = std::time::Instant::now();
+        assert!(matches!(other.block_for_beats(5, Duration::from_secs(60)), Err(TE::ShutDown)));
+        assert!(start.elapsed() < Duration::from_secs(1));
+
+        // Errors from ending the reports are returned
+        let dj = TheDJ::init_with_reporting().unwrap();
+        let report = ShutdownReport{seen: seen.clone(), ended: ended.clone(), fail: true};
+        assert!(dj.add_report(Box::new(report)).is_ok());
+        assert!(matches!(dj.shutdown(Duration::from_secs(5)), Err(TE::MaximumConfusion)));
+
+        Ok(())
+    }
+
+    // Panics once when read by the Deck, after being armed
+    #[derive(Debug)]
+    pub struct FaultyClock {clock: MockClock, armed: std::sync::atomic::AtomicBool}
+
+    impl Clock for FaultyClock {
+        fn now(&self) -> SystemTime { self.clock.now() }
+        fn monotonic(&self) -> Duration {
+            let deck = std::thread::current().name() == Some("thumper-deck");
+            if deck && self.armed.swap(false, std::sync::atomic::Ordering::SeqCst) {
+                panic!("Faulty clock");
+            }
+            self.clock.monotonic()
+        }
+    }
+
+    // Panics on its first run, errors on every run after
+    #[derive(Debug)]
+    pub struct FaultyReport {runs: Arc<Mutex<usize>>}
+
+    impl Report for FaultyReport {
+        fn duration(&self)        -> Result<Duration> {Ok(Duration::from_secs(0))}
+        fn init(&self)            -> Result<()> { Ok(()) }
+        fn run(&mut self, _: &Record) -> Result<()> {
+            let mut runs = self.runs.lock().unwrap();
+            *runs += 1;
+            if *runs == 1 { drop(runs); panic!("Faulty report") };
+            Err(TE::MaximumConfusion)
+        }
+        fn end(&self)             -> Result<()> { Ok(()) }
+    }
+
+    #[test]
+    fn supervision_test() -> io::Result<()> {
+
+        let clock = Arc::new(FaultyClock{clock: MockClock::new(SystemTime::now()), armed: Default::default()});
+        let dj = TheDJ::init_with_clock(clock.clone(), true).unwrap();
+        let runs = Arc::new(Mutex::new(0));
+        assert!(dj.add_report(Box::new(FaultyReport{runs: runs.clone()})).is_ok());
+        assert!(wait_for(|| dj.runtime_health().unwrap().is_healthy()));
+
+        let beat = dj.spin_new(String::from("survivor")).unwrap();
+        let id = beat.id;
+        for _ in 0..3 {
+            assert!(beat.now().is_ok());
+            clock.clock.advance(Duration::from_secs(1));
+        }
+        assert!(wait_for(|| dj.with_record(id, |r| r.raw_track.len() == 3).unwrap_or(false)));
+
+        // The Deck picks up from the records it last published
+        clock.armed.store(true, std::sync::atomic::Ordering::SeqCst);
+        assert!(wait_for(|| dj.runtime_health().unwrap().deck_restarts == 1));
+        assert_eq!(dj.runtime_health().unwrap().deck, RuntimeState::Running);
+        assert_eq!(dj.with_record(id, |r| r.raw_track.len()).unwrap(), 3);
+        assert!(beat.now().is_ok());
+        assert!(wait_for(|| dj.with_record(id, |r| r.raw_track.len() == 4).unwrap()));
+        assert!(dj.spin_new(String::from("newcomer")).is_ok());
+
+        // So does the Output, counting the errors from its reports
+        assert!(wait_for(|| {
+            clock.clock.advance(Duration::from_secs(1));
+            let _ = beat.now();
+            *runs.lock().unwrap() > 2
+        }));
+        let health = dj.runtime_health().unwrap();
+        assert_eq!(health.output_restarts, 1);
+        assert_eq!(health.output, Some(RuntimeState::Running));
+        assert!(health.report_errors > 0);
+        assert!(!health.is_healthy());
+
+        // The final run of the report errors too
+        assert!(matches!(dj.shutdown(Duration::from_secs(5)), Err(TE::MaximumConfusion)));
+        let health = dj.runtime_health().unwrap();
+        assert_eq!((health.deck, health.output), (RuntimeState::Stopped, Some(RuntimeState::Stopped)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn eviction_test() -> io::Result<()> {
+
+        let dj = TheDJ::builder().record_cap(3).build().unwrap();
+
+        // Terminal records make way for new ones, the oldest first
+        let first = dj.spin_new(String::from("first")).unwrap();
+        let first_id = first.id;
+        drop(first);
+        for name in ["second", "third"].iter() {
+            drop(dj.spin_new(name.to_string()).unwrap());
+        }
+        let live = dj.spin_new(String::from("fourth")).unwrap();
+        assert_eq!(live.id, first_id);
+        assert_eq!(dj.get_archive().unwrap().len(), 1);
+        assert_eq!(dj.get_archived(first_id).unwrap().cause, HealthState::Abandoned);
+        assert_eq!(dj.get_roster().unwrap().len(), 3);
+
+        // But live ones don't
+        let more = (0..2).map(|i| dj.spin_new(format!("more {}", i)).unwrap()).collect::<Vec<Beat>>();
+        assert_eq!(more.len(), 2);
+        assert!(matches!(dj.spin_new(String::from("too many")), Err(TE::MaximumCapacity)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn builder_test() -> io::Result<()> {
+
+        let clock = MockClock::new(SystemTime::now());
+        let (seen, ended) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(false)));
+        let dj = TheDJ::builder()
+            .record_cap(2)
+            .track_len(5)
+            .deck_tick(Duration::from_millis(100))
+            .drain_tick(Duration::from_millis(5))
+            .output_tick(Duration::from_millis(50))
+            .channel_bound(1)
+            .clock(clock.shared())
+            .report(Box::new(ShutdownReport{seen: seen.clone(), ended: ended.clone(), fail: false}))
+            .build()
+            .unwrap();
+
+        // Tracks are as long as configured, or as long as the record asks for
+        let short = dj.spin_new(String::from("short")).unwrap();
+        let long = dj.spin_new_with_track_len(String::from("long"), 8).unwrap();
+        for _ in 0..10 {
+            assert!(short.now().is_ok());
+            assert!(long.now().is_ok());
+            assert!(long.set_warmup(Duration::from_secs(0)).is_ok());
+            clock.advance(Duration::from_secs(1));
+        }
+        assert!(wait_for(|| dj.with_record(long.id, |r| r.raw_track.len() == 8).unwrap()));
+        assert_eq!(dj.with_record(short.id, |r| (r.track_len, r.raw_track.len())).unwrap(), (5, 5));
+
+        // Only so many records
+        assert!(matches!(dj.spin_new(String::from("third")), Err(TE::MaximumCapacity)));
+
+        // The report handed to the builder is run, once due
+        clock.advance(Duration::from_secs(3600));
+        assert!(short.now().is_ok());
+        assert!(dj.tick().is_ok());
+        assert!(!seen.lock().unwrap().is_empty());
+        assert!(dj.shutdown(Duration::from_secs(5)).is_ok());
+        assert!(*ended.lock().unwrap());
+
+        // Nonsense is refused
+        assert!(matches!(TheDJ::builder().track_len(0).build(), Err(TE::InvalidConfig(_))));
+        assert!(matches!(TheDJ::builder().drain_tick(Duration::from_secs(0)).build(), Err(TE::InvalidConfig(_))));
+        let dj = TheDJ::builder().build().unwrap();
+        assert!(matches!(dj.spin_new_with_track_len(String::from("empty"), 0), Err(TE::RegisterFail(_))));
+        assert_eq!(Config::default().track_len, BEAT_CAP);
+
+        // A quiet runtime is only Stalled after a couple of its ticks
+        let config = Config { output_tick: Duration::from_secs(3600), ..Config::default() };
+        assert_eq!(config.output_stall(), Duration::from_secs(7200));
+        assert_eq!(config.deck_stall(), STALL_THRESHOLD);
+
+        Ok(())
+    }
 }
 
 // ///////////////////////////////////////////////////////////////////////////
 // Helpers
 // /////////////////////////////////////////////////////////////////////
 
+// Poll until the condition holds, or give up after a few seconds
+fn wait_for<F: Fn() -> bool>(condition: F) -> bool {
+    let start = std::time::Instant::now();
+    while start.elapsed() < Duration::from_secs(5) {
+        if condition() { return true };
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    false
+}
+
 fn sum_each_int(n: u64) -> u64 {
     n * (n + 1) / 2
 }
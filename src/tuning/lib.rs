@@ -4,6 +4,7 @@ use std::collections::HashMap;
 
 
 use crate::{DM2Deck, TE, Record};
+use crate::core::BetterTo;
 
 // ////////////////////////////////////////////////////////////////
 // Type less
@@ -22,12 +23,6 @@ pub enum DM2AutoTuner {
 // ////////////////////////////////////////////////////////////////
 // Primitive level tunings that can be applied
 // ///////////////////////////////////////////////////
-#[derive(Debug)]
-pub enum BetterTo {
-    GoOver,
-    GoUnder,
-}
-
 #[derive(Debug)]
 pub enum Tuning {
     ExpectedFreqMillis(u32),
@@ -37,20 +32,6 @@ pub enum Tuning {
     WarmupMillis(u32),
 }
 
-
-#[derive(PartialEq, Debug, Clone)]
-pub enum ConfidenceLevel {
-    Very,        // Most confident
-    Likely,      //
-    Maybe,       //
-    Nil,         //
-    UserDefined, // Least confident
-}
-
-
-
-
-
 // ////////////////////////////////////////////////////////////////
 // Attunement Trait
 // ///////////////////////////////////////////////////